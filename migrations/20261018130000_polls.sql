@@ -0,0 +1,26 @@
+CREATE TABLE Poll (
+    message_id INTEGER NOT NULL PRIMARY KEY,
+    channel_id INTEGER NOT NULL,
+    guild_id INTEGER NOT NULL,
+    author_id INTEGER NOT NULL,
+    question TEXT NOT NULL,
+    closes_at DATETIME,
+    closed BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX idx_poll_guild ON Poll(guild_id, closed);
+
+CREATE TABLE PollChoice (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    poll_id INTEGER NOT NULL REFERENCES Poll(message_id) ON DELETE CASCADE,
+    icon TEXT NOT NULL,
+    owner_id INTEGER NOT NULL,
+    text TEXT NOT NULL
+);
+
+CREATE TABLE PollVote (
+    poll_id INTEGER NOT NULL REFERENCES Poll(message_id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL,
+    choice_id INTEGER NOT NULL REFERENCES PollChoice(id) ON DELETE CASCADE,
+    PRIMARY KEY (poll_id, user_id)
+);
@@ -0,0 +1,4 @@
+ALTER TABLE Poll ADD COLUMN reminder_role_id INTEGER;
+ALTER TABLE Poll ADD COLUMN reminded BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX idx_poll_closes_at ON Poll(closed, closes_at);
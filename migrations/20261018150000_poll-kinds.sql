@@ -0,0 +1,17 @@
+ALTER TABLE Poll ADD COLUMN kind TEXT NOT NULL DEFAULT 'Single';
+ALTER TABLE Poll ADD COLUMN max_picks INTEGER NOT NULL DEFAULT 1;
+
+-- Multi-select and ranked ballots hold more than one vote per user
+CREATE TABLE PollBallot (
+    poll_id INTEGER NOT NULL REFERENCES Poll(message_id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL,
+    choice_id INTEGER NOT NULL REFERENCES PollChoice(id) ON DELETE CASCADE,
+    rank INTEGER NOT NULL DEFAULT 1,
+    PRIMARY KEY (poll_id, user_id, choice_id)
+);
+
+INSERT INTO PollBallot (poll_id, user_id, choice_id)
+SELECT poll_id, user_id, choice_id FROM PollVote;
+
+DROP TABLE PollVote;
+ALTER TABLE PollBallot RENAME TO PollVote;
@@ -0,0 +1 @@
+ALTER TABLE SimpleCommands ADD COLUMN uses INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,4 @@
+ALTER TABLE SimpleCommands ADD COLUMN title TEXT;
+ALTER TABLE SimpleCommands ADD COLUMN colour INTEGER;
+-- A URL for embed commands, the path on disk for image commands
+ALTER TABLE SimpleCommands ADD COLUMN image TEXT;
@@ -0,0 +1,5 @@
+ALTER TABLE SimpleCommands ADD COLUMN cooldown INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE SimpleCommands ADD COLUMN user_cooldown INTEGER NOT NULL DEFAULT 0;
+-- Space separated channel ids, the command works anywhere when it's NULL
+ALTER TABLE SimpleCommands ADD COLUMN allowed_channels TEXT;
+ALTER TABLE SimpleCommands ADD COLUMN required_role INTEGER;
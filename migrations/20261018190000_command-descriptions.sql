@@ -0,0 +1,2 @@
+ALTER TABLE SimpleCommands ADD COLUMN description TEXT;
+ALTER TABLE SimpleCommands ADD COLUMN last_used DATETIME;
@@ -0,0 +1,15 @@
+CREATE TABLE CallResponse (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    expression TEXT NOT NULL,
+    cooldown INTEGER NOT NULL DEFAULT 60,
+    text TEXT,
+    attachment TEXT
+);
+
+CREATE INDEX idx_call_response_guild ON CallResponse(guild_id);
+
+-- The responses that used to live in assets/call_responses/data.json
+INSERT INTO CallResponse (guild_id, expression, cooldown, attachment) VALUES
+    (111135289648349184, '(?i)\btuturu\b', 60, './assets/call_responses/Tuturu.png'),
+    (111135289648349184, '(?i)zuzuru', 60, './assets/call_responses/Zuzuru.png');
@@ -0,0 +1 @@
+ALTER TABLE CallResponse ADD COLUMN weight INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,6 @@
+ALTER TABLE CallResponse ADD COLUMN cooldown_scope TEXT NOT NULL DEFAULT 'Global';
+-- Space separated channel ids, an empty allow list means every channel
+ALTER TABLE CallResponse ADD COLUMN allowed_channels TEXT;
+ALTER TABLE CallResponse ADD COLUMN denied_channels TEXT;
+-- Percent chance of replying when the response is picked
+ALTER TABLE CallResponse ADD COLUMN probability INTEGER NOT NULL DEFAULT 100;
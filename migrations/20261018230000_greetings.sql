@@ -0,0 +1,15 @@
+CREATE TABLE Greeting (
+    guild_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    -- One of the | separated templates is picked at random
+    templates TEXT NOT NULL,
+    -- Seconds before the same person is greeted again
+    timeout INTEGER NOT NULL DEFAULT 36000,
+    -- Greet the first message of every day instead of waiting for the timeout
+    daily BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (guild_id, user_id)
+);
+
+-- The greeting that used to be hardcoded in events/blob.rs
+INSERT INTO Greeting (guild_id, user_id, templates, timeout) VALUES
+    (111135289648349184, 104908485266817024, 'Hi Blob!', 36000);
@@ -0,0 +1,12 @@
+CREATE TABLE SelfRole (
+    role_id INTEGER PRIMARY KEY,
+    guild_id INTEGER NOT NULL,
+    description TEXT,
+    category TEXT,
+    colour INTEGER,
+    -- Joining a role removes the other roles of the same group
+    exclusive_group TEXT,
+    max_members INTEGER
+);
+
+CREATE INDEX idx_self_role_guild ON SelfRole(guild_id);
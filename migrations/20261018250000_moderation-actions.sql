@@ -0,0 +1,13 @@
+CREATE TABLE ModerationAction (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    actor_id INTEGER NOT NULL,
+    target_id INTEGER NOT NULL,
+    reason TEXT,
+    -- Seconds the target was timed out for
+    duration INTEGER,
+    source TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX idx_moderation_action_target ON ModerationAction(guild_id, target_id);
@@ -0,0 +1,12 @@
+CREATE TABLE Warning (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    actor_id INTEGER NOT NULL,
+    points INTEGER NOT NULL,
+    reason TEXT,
+    created_at INTEGER NOT NULL,
+    pardoned BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX idx_warning_user ON Warning(guild_id, user_id, created_at);
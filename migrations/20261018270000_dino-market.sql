@@ -0,0 +1,8 @@
+INSERT INTO DinoTransactionType (type) VALUES ('TRADE');
+
+CREATE TABLE DinoListing (
+    dino_id INTEGER NOT NULL PRIMARY KEY REFERENCES Dino(id) ON DELETE CASCADE,
+    seller_id TEXT NOT NULL REFERENCES DinoUser(id),
+    note TEXT,
+    created_at INTEGER NOT NULL
+);
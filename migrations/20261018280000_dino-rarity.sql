@@ -0,0 +1 @@
+ALTER TABLE Dino ADD COLUMN rarity INTEGER NOT NULL DEFAULT 0;
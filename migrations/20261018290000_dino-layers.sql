@@ -0,0 +1,7 @@
+ALTER TABLE Dino ADD COLUMN background TEXT NOT NULL DEFAULT '';
+ALTER TABLE Dino ADD COLUMN hat TEXT NOT NULL DEFAULT '';
+ALTER TABLE Dino ADD COLUMN accessory TEXT NOT NULL DEFAULT '';
+ALTER TABLE Dino ADD COLUMN hue INTEGER NOT NULL DEFAULT 0;
+
+DROP INDEX idx_body_mouth_eyes;
+CREATE UNIQUE INDEX idx_dino_parts ON Dino(body, mouth, eyes, background, hat, accessory, hue);
@@ -0,0 +1,10 @@
+ALTER TABLE Dino ADD COLUMN parent_a INTEGER;
+ALTER TABLE Dino ADD COLUMN parent_b INTEGER;
+
+-- Slurped dinos are kept here so their children can still show where they came from
+CREATE TABLE DinoAncestor (
+    id INTEGER NOT NULL PRIMARY KEY,
+    name TEXT NOT NULL,
+    parent_a INTEGER,
+    parent_b INTEGER
+);
@@ -0,0 +1,37 @@
+CREATE TABLE DinoOwnership (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    dino_id INTEGER NOT NULL REFERENCES Dino(id) ON DELETE CASCADE,
+    owner_id TEXT NOT NULL,
+    previous_owner_id TEXT,
+    kind TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX idx_dino_ownership ON DinoOwnership(dino_id);
+
+-- Older gifts and trades weren't timestamped so they're dated to the hatch
+INSERT INTO DinoOwnership (dino_id, owner_id, kind, created_at)
+SELECT Dino.id,
+    COALESCE(
+        (SELECT t.gifter_id FROM DinoTransactions t
+        WHERE t.dino_id = Dino.id AND t.type IN ('GIFT', 'TRADE')
+        ORDER BY t.id LIMIT 1),
+        Dino.owner_id
+    ),
+    'Hatch',
+    CAST(strftime('%s', Dino.created_at) AS INTEGER)
+FROM Dino
+ORDER BY Dino.id;
+
+INSERT INTO DinoOwnership (dino_id, owner_id, previous_owner_id, kind, created_at)
+SELECT t.dino_id,
+    t.user_id,
+    t.gifter_id,
+    CASE t.type WHEN 'TRADE' THEN 'Trade' ELSE 'Gift' END,
+    CAST(strftime('%s', Dino.created_at) AS INTEGER)
+FROM DinoTransactions t
+INNER JOIN Dino ON Dino.id = t.dino_id
+WHERE t.type IN ('GIFT', 'TRADE')
+ORDER BY t.id;
+
+UPDATE Dino SET owners = (SELECT COUNT(*) FROM DinoOwnership WHERE dino_id = Dino.id);
@@ -0,0 +1,451 @@
+use std::path::Path;
+
+use anyhow::bail;
+use poise::serenity_prelude::{
+    Attachment, AutocompleteChoice, ChannelId, CreateEmbed, GuildId, Mentionable,
+};
+use poise::{ChoiceParameter, CreateReply};
+use regex::Regex;
+
+use crate::{
+    commands::uploads::{remove_upload, save_upload},
+    common::{bail_reply, ephemeral_reply},
+    config::parse_channels,
+    events::{
+        channels_to_db, fetch_call_responses, get_attachment, pick_weighted, reload_call_responses,
+        CallResponse, CompiledResponses, CooldownScope,
+    },
+    Context, Result,
+};
+
+const UPLOADS_PATH: &str = "./assets/call_responses";
+const DEFAULT_COOLDOWN: u32 = 60;
+const MAX_LISTED_RESPONSES: usize = 25;
+
+#[poise::command(
+    guild_only,
+    slash_command,
+    subcommands("add", "edit", "remove", "list", "test"),
+    required_permissions = "MODERATE_MEMBERS",
+    default_member_permissions = "MODERATE_MEMBERS"
+)]
+pub async fn callresponse(_ctx: Context<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// MOD ONLY: Reply to every message matching a regex
+#[poise::command(guild_only, slash_command)]
+#[allow(clippy::too_many_arguments)]
+async fn add(
+    ctx: Context<'_>,
+    #[description = "The regex a message has to match, like (?i)\\btuturu\\b"] expression: String,
+    #[description = "What to reply with"] text: Option<String>,
+    #[description = "A file to reply with"] attachment: Option<Attachment>,
+    #[description = "Seconds before the trigger can go off again, 60 by default"] cooldown: Option<
+        u32,
+    >,
+    #[description = "How often it's picked over other matching triggers, 1 by default"]
+    #[min = 1]
+    weight: Option<u32>,
+    #[description = "Whether the cooldown is for the whole server, a channel or a person"]
+    cooldown_scope: Option<CooldownScope>,
+    #[description = "The only channels it goes off in, as channel mentions"]
+    allowed_channels: Option<String>,
+    #[description = "Channels it never goes off in, as channel mentions"] denied_channels: Option<
+        String,
+    >,
+    #[description = "Percent chance of replying when it goes off, 100 by default"]
+    #[min = 0]
+    #[max = 100]
+    probability: Option<u8>,
+) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /callresponse add to be guild only.");
+
+    if let Err(reason) = ensure_valid_expression(&expression) {
+        return bail_reply(ctx, reason.to_string()).await;
+    }
+    if text.is_none() && attachment.is_none() {
+        return bail_reply(ctx, "The trigger needs a text or a file to reply with.").await;
+    }
+
+    let channels = parse_channel_option(allowed_channels.as_deref())
+        .and_then(|allowed| Ok((allowed, parse_channel_option(denied_channels.as_deref())?)));
+    let (allowed_channels, denied_channels) = match channels {
+        Ok((allowed, denied)) => (allowed.unwrap_or_default(), denied.unwrap_or_default()),
+        Err(reason) => return bail_reply(ctx, reason.to_string()).await,
+    };
+
+    let db_guild_id = guild_id.get() as i64;
+    let cooldown = cooldown.unwrap_or(DEFAULT_COOLDOWN);
+    let weight = weight.unwrap_or(1);
+    let cooldown_scope = cooldown_scope.unwrap_or(CooldownScope::Global);
+    let allowed_channels = channels_to_db(&allowed_channels);
+    let denied_channels = channels_to_db(&denied_channels);
+    let probability = probability.unwrap_or(100);
+
+    let mut transaction = ctx.data().database.begin().await?;
+    let id = sqlx::query_scalar!(
+        r#"INSERT INTO CallResponse (
+            guild_id, expression, cooldown, text, weight, cooldown_scope, allowed_channels,
+            denied_channels, probability
+        )
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        RETURNING id AS "id!""#,
+        db_guild_id,
+        expression,
+        cooldown,
+        text,
+        weight,
+        cooldown_scope,
+        allowed_channels,
+        denied_channels,
+        probability
+    )
+    .fetch_one(&mut *transaction)
+    .await?;
+
+    if let Some(attachment) = &attachment {
+        let path = match save_response_upload(guild_id, id, attachment).await {
+            Ok(path) => path,
+            Err(reason) => return bail_reply(ctx, reason.to_string()).await,
+        };
+        sqlx::query!(
+            "UPDATE CallResponse SET attachment = ? WHERE id = ?",
+            path,
+            id
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+    transaction.commit().await?;
+
+    reload_call_responses(&ctx.data().database, guild_id).await?;
+    ctx.send(ephemeral_reply(format!("Trigger #{id} has been added.")))
+        .await?;
+
+    Ok(())
+}
+
+/// MOD ONLY: Change the parts of a trigger you fill in
+#[poise::command(guild_only, slash_command)]
+#[allow(clippy::too_many_arguments)]
+async fn edit(
+    ctx: Context<'_>,
+    #[description = "The trigger you want to change"]
+    #[autocomplete = "autocomplete_call_responses"]
+    trigger: i64,
+    #[description = "The regex a message has to match"] expression: Option<String>,
+    #[description = "What to reply with"] text: Option<String>,
+    #[description = "A new file to reply with"] attachment: Option<Attachment>,
+    #[description = "Seconds before the trigger can go off again"] cooldown: Option<u32>,
+    #[description = "How often it's picked over other matching triggers"]
+    #[min = 1]
+    weight: Option<u32>,
+    #[description = "Whether the cooldown is for the whole server, a channel or a person"]
+    cooldown_scope: Option<CooldownScope>,
+    #[description = "The only channels it goes off in as channel mentions, or none"]
+    allowed_channels: Option<String>,
+    #[description = "Channels it never goes off in as channel mentions, or none"]
+    denied_channels: Option<String>,
+    #[description = "Percent chance of replying when it goes off"]
+    #[min = 0]
+    #[max = 100]
+    probability: Option<u8>,
+) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /callresponse edit to be guild only.");
+
+    let Some(response) = find_call_response(ctx, guild_id, trigger).await? else {
+        return bail_reply(ctx, "That trigger does not exist.").await;
+    };
+    if let Some(expression) = &expression {
+        if let Err(reason) = ensure_valid_expression(expression) {
+            return bail_reply(ctx, reason.to_string()).await;
+        }
+    }
+    let channels = parse_channel_option(allowed_channels.as_deref())
+        .and_then(|allowed| Ok((allowed, parse_channel_option(denied_channels.as_deref())?)));
+    let (allowed_channels, denied_channels) = match channels {
+        Ok(channels) => channels,
+        Err(reason) => return bail_reply(ctx, reason.to_string()).await,
+    };
+
+    let mut stored_attachment = response.attachment.clone();
+    if let Some(attachment) = &attachment {
+        match save_response_upload(guild_id, response.id, attachment).await {
+            Ok(path) => {
+                if let Some(old_path) = stored_attachment.filter(|p| *p != path) {
+                    remove_response_upload(guild_id, &old_path).await;
+                }
+                stored_attachment = Some(path);
+            }
+            Err(reason) => return bail_reply(ctx, reason.to_string()).await,
+        }
+    }
+
+    let expression = expression.unwrap_or(response.expression);
+    let text = text.or(response.text);
+    let cooldown = cooldown.map_or(response.cooldown, i64::from);
+    let weight = weight.map_or(response.weight, i64::from);
+    let cooldown_scope = cooldown_scope.unwrap_or(response.cooldown_scope);
+    let allowed_channels = channels_to_db(
+        allowed_channels
+            .as_deref()
+            .unwrap_or(&response.allowed_channels),
+    );
+    let denied_channels = channels_to_db(
+        denied_channels
+            .as_deref()
+            .unwrap_or(&response.denied_channels),
+    );
+    let probability = probability.map_or(response.probability, i64::from);
+    sqlx::query!(
+        "UPDATE CallResponse
+        SET expression = ?, text = ?, attachment = ?, cooldown = ?, weight = ?, cooldown_scope = ?,
+            allowed_channels = ?, denied_channels = ?, probability = ?
+        WHERE id = ?",
+        expression,
+        text,
+        stored_attachment,
+        cooldown,
+        weight,
+        cooldown_scope,
+        allowed_channels,
+        denied_channels,
+        probability,
+        response.id
+    )
+    .execute(&ctx.data().database)
+    .await?;
+
+    reload_call_responses(&ctx.data().database, guild_id).await?;
+    ctx.send(ephemeral_reply("The trigger has been updated."))
+        .await?;
+
+    Ok(())
+}
+
+/// MOD ONLY: Stop replying to a trigger
+#[poise::command(guild_only, slash_command)]
+async fn remove(
+    ctx: Context<'_>,
+    #[description = "The trigger you want to remove"]
+    #[autocomplete = "autocomplete_call_responses"]
+    trigger: i64,
+) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /callresponse remove to be guild only.");
+
+    let Some(response) = find_call_response(ctx, guild_id, trigger).await? else {
+        return bail_reply(ctx, "That trigger does not exist.").await;
+    };
+
+    sqlx::query!("DELETE FROM CallResponse WHERE id = ?", response.id)
+        .execute(&ctx.data().database)
+        .await?;
+    if let Some(path) = &response.attachment {
+        remove_response_upload(guild_id, path).await;
+    }
+
+    reload_call_responses(&ctx.data().database, guild_id).await?;
+    ctx.send(ephemeral_reply("The trigger has been removed."))
+        .await?;
+
+    Ok(())
+}
+
+/// MOD ONLY: Show every trigger of this server
+#[poise::command(guild_only, slash_command)]
+async fn list(ctx: Context<'_>) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /callresponse list to be guild only.");
+
+    let responses = fetch_call_responses(&ctx.data().database, guild_id).await?;
+    if responses.is_empty() {
+        return bail_reply(ctx, "This server has no triggers yet.").await;
+    }
+
+    let mut lines = responses
+        .iter()
+        .take(MAX_LISTED_RESPONSES)
+        .map(describe_response)
+        .collect::<Vec<_>>();
+    if responses.len() > MAX_LISTED_RESPONSES {
+        lines.push(format!(
+            "...and {} more",
+            responses.len() - MAX_LISTED_RESPONSES
+        ));
+    }
+
+    let embed = CreateEmbed::default()
+        .colour(0x5865F2)
+        .title("Call responses")
+        .description(lines.join("\n"));
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+/// MOD ONLY: See which trigger a message would set off
+#[poise::command(guild_only, slash_command)]
+async fn test(
+    ctx: Context<'_>,
+    #[description = "The message you want to test"] message: String,
+) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /callresponse test to be guild only.");
+
+    let responses = fetch_call_responses(&ctx.data().database, guild_id).await?;
+    let responses = CompiledResponses::new(responses);
+    let candidates = responses.matching(&message).collect::<Vec<_>>();
+    let Some(response) = pick_weighted(&candidates, &mut rand::thread_rng()) else {
+        return bail_reply(ctx, "No trigger matches that message.").await;
+    };
+
+    let total_weight: i64 = candidates.iter().map(|r| r.weight).sum();
+    let mut content = candidates
+        .iter()
+        .map(|r| {
+            let chance = r.weight * 100 / total_weight;
+            format!("Trigger #{} `{}` matches ({chance}%)", r.id, r.expression)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    content = format!("{content}\n\nTrigger #{} replied:", response.id);
+    if let Some(text) = &response.text {
+        content = format!("{content}\n{text}");
+    }
+
+    let mut reply = ephemeral_reply(content);
+    if let Some(attachment) = response.attachment.as_deref() {
+        if let Some(file) = get_attachment(attachment).await {
+            reply = reply.attachment(file);
+        }
+    }
+    ctx.send(reply).await?;
+
+    Ok(())
+}
+
+fn ensure_valid_expression(expression: &str) -> Result<()> {
+    if let Err(e) = Regex::new(expression) {
+        bail!("`{expression}` is not a valid regex: {e}");
+    }
+
+    Ok(())
+}
+
+/// Parse a list of channels someone filled in, where `none` clears the list.
+fn parse_channel_option(channels: Option<&str>) -> Result<Option<Vec<ChannelId>>> {
+    match channels {
+        None => Ok(None),
+        Some(channels) if channels.trim().eq_ignore_ascii_case("none") => Ok(Some(Vec::new())),
+        Some(channels) => parse_channels(channels).map(Some),
+    }
+}
+
+fn describe_response(response: &CallResponse) -> String {
+    let reply = match (&response.text, &response.attachment) {
+        (Some(text), Some(_)) => format!("{text} + file"),
+        (Some(text), None) => text.clone(),
+        (None, Some(_)) => "a file".to_string(),
+        (None, None) => "nothing".to_string(),
+    };
+
+    let mut details = vec![
+        format!(
+            "{}s {} cooldown",
+            response.cooldown,
+            response.cooldown_scope.name().to_lowercase()
+        ),
+        format!("weight {}", response.weight),
+    ];
+    if response.probability < 100 {
+        details.push(format!("{}% chance", response.probability));
+    }
+    if !response.allowed_channels.is_empty() {
+        details.push(format!(
+            "in {}",
+            mention_channels(&response.allowed_channels)
+        ));
+    }
+    if !response.denied_channels.is_empty() {
+        details.push(format!(
+            "not in {}",
+            mention_channels(&response.denied_channels)
+        ));
+    }
+
+    format!(
+        "**#{}** `{}` ({}): {reply}",
+        response.id,
+        response.expression,
+        details.join(", ")
+    )
+}
+
+fn mention_channels(channels: &[ChannelId]) -> String {
+    channels
+        .iter()
+        .map(|c| c.mention().to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+async fn find_call_response(
+    ctx: Context<'_>,
+    guild_id: GuildId,
+    id: i64,
+) -> Result<Option<CallResponse>> {
+    let responses = fetch_call_responses(&ctx.data().database, guild_id).await?;
+    Ok(responses.into_iter().find(|r| r.id == id))
+}
+
+async fn save_response_upload(guild_id: GuildId, id: i64, upload: &Attachment) -> Result<String> {
+    let folder = Path::new(UPLOADS_PATH).join(guild_id.to_string());
+    save_upload(&folder, &id.to_string(), upload).await
+}
+
+/// Only remove files uploaded through the command, not the ones that ship with the bot.
+async fn remove_response_upload(guild_id: GuildId, path: &str) {
+    let folder = Path::new(UPLOADS_PATH).join(guild_id.to_string());
+    if Path::new(path).starts_with(folder) {
+        remove_upload(path).await;
+    }
+}
+
+async fn autocomplete_call_responses<'a>(
+    ctx: Context<'a>,
+    partial: &'a str,
+) -> impl Iterator<Item = AutocompleteChoice> + 'a {
+    let guild_id = ctx.guild_id().map(|g| g.get() as i64).unwrap_or_default();
+    let partial = format!("%{partial}%");
+
+    let suggestions = sqlx::query!(
+        r#"SELECT id AS "id!", expression FROM CallResponse
+        WHERE guild_id = ? AND (expression LIKE ? OR text LIKE ?)
+        ORDER BY id LIMIT 25"#,
+        guild_id,
+        partial,
+        partial
+    )
+    .fetch_all(&ctx.data().database)
+    .await
+    .unwrap_or_else(|e| {
+        eprintln!("Error while trying to suggest autocomplete for '{partial}': {e}");
+        vec![]
+    });
+
+    suggestions.into_iter().map(|r| {
+        let name = format!("#{} {}", r.id, r.expression);
+        AutocompleteChoice::new(name.chars().take(100).collect::<String>(), r.id)
+    })
+}
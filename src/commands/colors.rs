@@ -5,11 +5,10 @@ use chrono::{NaiveDateTime, Utc};
 use poise::serenity_prelude::{EditRole, Member, Mention, Role};
 use rand::Rng;
 use sqlx::SqlitePool;
-use tokio::sync::Mutex;
 
 use crate::{
     common::{bail_reply, ephemeral_reply},
-    config::{COLOR_ANCHOR_ROLE, DEFAULT_GAMBLE_FAIL_CHANCE, RANDOM_COLOR_COOLDOWN, SUB_ROLE},
+    config::{save_guild_config_value, ConfigKey},
     Context, Result,
 };
 
@@ -17,8 +16,7 @@ use crate::{
     guild_only,
     slash_command,
     prefix_command,
-    subcommands("change", "random", "favorite", "lazy", "gamble", "setgamblechance"),
-    custom_data = "Mutex::new(DEFAULT_GAMBLE_FAIL_CHANCE)"
+    subcommands("change", "random", "favorite", "lazy", "gamble", "setgamblechance")
 )]
 pub async fn color(_ctx: Context<'_>) -> Result<()> {
     Ok(())
@@ -42,7 +40,7 @@ async fn change(
         return bail_reply(ctx, "I could not find your roles.").await;
     };
 
-    if let Err(reason) = reject_non_subs(&member).await {
+    if let Err(reason) = reject_non_subs(ctx, &member).await {
         return bail_reply(ctx, reason.to_string()).await;
     }
 
@@ -69,7 +67,7 @@ async fn random(ctx: Context<'_>) -> Result<()> {
         return bail_reply(ctx, "I could not find your roles.").await;
     };
 
-    if let Err(reason) = reject_non_subs(&member).await {
+    if let Err(reason) = reject_non_subs(ctx, &member).await {
         return bail_reply(ctx, reason.to_string()).await;
     }
 
@@ -101,20 +99,19 @@ async fn gamble(ctx: Context<'_>) -> Result<()> {
         return bail_reply(ctx, "I could not find your roles").await;
     };
 
-    if let Err(reason) = reject_non_subs(&member).await {
+    if let Err(reason) = reject_non_subs(ctx, &member).await {
         return bail_reply(ctx, reason.to_string()).await;
     }
 
-    let custom_data = ctx.parent_commands()[0]
-        .custom_data
-        .downcast_ref::<Mutex<u8>>()
-        .expect("Expected to have passed the gamble chance as custom_data");
-    let gamble_chance = custom_data.lock().await;
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected colors commands to be guild only.");
+    let gamble_chance = ctx.data().guild_config(guild_id).await.gamble_fail_chance;
     let roll: u8 = {
         let mut rng = rand::thread_rng();
         rng.gen_range(0..=100)
     };
-    if roll > *gamble_chance {
+    if roll > gamble_chance {
         return bail_reply(ctx, "Yay! You get to keep your color!").await;
     }
 
@@ -147,12 +144,14 @@ async fn change_color(
         bail!("Could not get guild from guild_id");
     };
 
+    let anchor_role_id = ctx.data().guild_config(guild_id).await.color_anchor_role;
+
     let color = color.unwrap_or_else(generate_random_hex_color);
     let role_name = format!("#{color:06X}");
     let role = match guild.role_by_name(&role_name) {
         Some(role) => role.clone(),
         None => {
-            let Some(anchor_role) = guild.roles.get(&COLOR_ANCHOR_ROLE) else {
+            let Some(anchor_role) = guild.roles.get(&anchor_role_id) else {
                 bail!(
                     "The anchor role was not found, \
                 unable to create a role with at the correct position."
@@ -217,7 +216,7 @@ pub async fn lazy(ctx: Context<'_>) -> Result<()> {
         return bail_reply(ctx, "Could not find your roles").await;
     };
 
-    if let Err(reason) = reject_non_subs(&member).await {
+    if let Err(reason) = reject_non_subs(ctx, &member).await {
         return bail_reply(ctx, reason.to_string()).await;
     }
 
@@ -310,12 +309,12 @@ async fn setgamblechance(
         return bail_reply(ctx, "Please provide a number between 0 and 100").await;
     }
 
-    let custom_data = ctx.parent_commands()[0]
-        .custom_data
-        .downcast_ref::<Mutex<u8>>()
-        .expect("Expected to have passed the gamble chance as custom_data");
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected colors commands to be guild only.");
+    let value = percent.to_string();
+    save_guild_config_value(ctx.data(), guild_id, ConfigKey::GambleFailChance, &value).await?;
 
-    *custom_data.lock().await = percent;
     ctx.send(ephemeral_reply(format!(
         "Gamble chance has been set to {percent}%"
     )))
@@ -324,7 +323,7 @@ async fn setgamblechance(
     Ok(())
 }
 
-fn to_color(hexcode: &str) -> Option<u32> {
+pub fn to_color(hexcode: &str) -> Option<u32> {
     let hexcode = hexcode.strip_prefix('#').unwrap_or(hexcode);
 
     match u32::from_str_radix(hexcode, 16) {
@@ -404,8 +403,15 @@ async fn reject_on_cooldown(ctx: Context<'_>) -> Result<()> {
     .await
     .context("Something went wrong while trying to fetch your cooldowns")?;
 
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected colors commands to be guild only.");
     let now = Utc::now().naive_utc();
-    let cooldown_duration = RANDOM_COLOR_COOLDOWN;
+    let cooldown_duration = ctx
+        .data()
+        .guild_config(guild_id)
+        .await
+        .random_color_cooldown;
 
     let permitted_time_from_random = row.last_random + cooldown_duration;
     let permitted_time_from_loss = row.last_loss + cooldown_duration;
@@ -427,8 +433,9 @@ async fn reject_on_cooldown(ctx: Context<'_>) -> Result<()> {
     Ok(())
 }
 
-async fn reject_non_subs(member: &Member) -> Result<()> {
-    if !member.roles.contains(&SUB_ROLE) {
+async fn reject_non_subs(ctx: Context<'_>, member: &Member) -> Result<()> {
+    let sub_role = ctx.data().guild_config(member.guild_id).await.sub_role;
+    if !member.roles.contains(&sub_role) {
         bail!("Yay! You get to keep your white color!");
     }
 
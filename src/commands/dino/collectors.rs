@@ -112,7 +112,7 @@ async fn handle_dino_collector(
             .create_response(ctx, response(ephemeral_text_message(&content)))
             .await?;
     } else {
-        let (gifts, hotness) = calculate_dino_score(&mut transaction, dino_id).await?;
+        let (owners, hotness) = calculate_dino_score(&mut transaction, dino_id).await?;
 
         let old_embed = interaction.message.embeds[0].clone();
         let old_image = old_embed.image.clone();
@@ -121,7 +121,7 @@ async fn handle_dino_collector(
                 .title(&dino_name)
                 .footer(CreateEmbedFooter::new(format!(
                     "{dino_name} is worth {} Dino Bucks!\nHotness Rating: {}",
-                    quirkify_worth(gifts + 1), // +1 because the hatcher is also an owner
+                    quirkify_worth(owners),
                     quirkify_hotness(hotness)
                 )));
 
@@ -268,21 +268,24 @@ async fn calculate_dino_score(conn: &mut SqliteConnection, dino_id: i64) -> Resu
 
     let mut covets = 0;
     let mut shuns = 0;
-    let mut gifts = 0; // gifts *should* be synced with the amount of owners a dino has had
 
     for entry in row.into_iter() {
         match entry.type_.as_ref() {
             "COVET" => covets += entry.count,
             "SHUN" => shuns += entry.count,
-            "GIFT" => gifts += entry.count,
             _ => {}
         }
     }
 
     let hotness = covets - shuns;
-    update_dino_score(conn, dino_id, hotness).await?;
+    update_dino_score(&mut *conn, dino_id, hotness).await?;
 
-    Ok((gifts, hotness))
+    // Owners are counted from the dino's ownership history
+    let owners = sqlx::query_scalar!("SELECT owners FROM Dino WHERE id = ?", dino_id)
+        .fetch_one(conn)
+        .await?;
+
+    Ok((owners, hotness))
 }
 
 async fn update_dino_score(conn: &mut SqliteConnection, dino_id: i64, hotness: i64) -> Result<()> {
@@ -1,34 +1,138 @@
 use anyhow::{bail, Context as AnyhowContext};
-use chrono::{NaiveDateTime, Utc};
-use image::{imageops::overlay, io::Reader, ImageBuffer, ImageOutputFormat, RgbaImage};
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use image::imageops::{colorops::huerotate_in_place, overlay};
+use image::{io::Reader, ImageBuffer, ImageOutputFormat, RgbaImage};
 use poise::serenity_prelude::{
     ButtonStyle, CreateActionRow, CreateAttachment, CreateButton, CreateEmbed, CreateEmbedAuthor,
     CreateEmbedFooter, User, UserId,
 };
 use poise::CreateReply;
-use rand::{seq::SliceRandom, thread_rng};
+use rand::{seq::SliceRandom, thread_rng, Rng};
+use serde::Deserialize;
 use serenity::all::Member;
 use sqlx::error::DatabaseError;
 use sqlx::sqlite::SqliteError;
-use sqlx::{FromRow, QueryBuilder, Row, Sqlite, SqliteExecutor, SqlitePool};
+use sqlx::{FromRow, QueryBuilder, Row, Sqlite, SqliteConnection, SqliteExecutor, SqlitePool};
 
+use std::collections::HashMap;
+use std::fmt::Display;
 use std::io::Cursor;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::OnceLock;
 
+use super::history::{history, record_ownership, OwnershipKind};
+use super::market::{market, trade};
 use crate::common::{avatar_url, ephemeral_reply, name as get_name, pick_best_x_dice_rolls};
 use crate::common::{bail_reply, embed_message, ephemeral_text_message, response};
-use crate::config::{DINO_GIFTING_COOLDOWN, DINO_SLURP_COOLDOWN, SUB_ROLE};
 use crate::{Context, Result};
 
 // TODO: Use DateTime<Utc> instead of NaiveDateTime for database times
 
+#[derive(Debug)]
+struct Fragment {
+    path: PathBuf,
+    weight: u32,
+}
+
 #[derive(Debug, Default)]
 struct Fragments {
-    bodies: Vec<PathBuf>,
-    mouths: Vec<PathBuf>,
-    eyes: Vec<PathBuf>,
+    bodies: Vec<Fragment>,
+    mouths: Vec<Fragment>,
+    eyes: Vec<Fragment>,
+    backgrounds: Vec<Fragment>,
+    hats: Vec<Fragment>,
+    accessories: Vec<Fragment>,
+    chances: LayerChances,
+}
+
+impl Fragments {
+    /// How rare a combination of a body, mouth and eyes is.
+    fn rarity(&self, body: &Fragment, mouth: &Fragment, eyes: &Fragment) -> Rarity {
+        let chance = relative_chance(&self.bodies, body)
+            * relative_chance(&self.mouths, mouth)
+            * relative_chance(&self.eyes, eyes);
+
+        Rarity::from_odds(1.0 / chance)
+    }
+}
+
+/// Optional layers that go on top of (or behind) the body, mouth and eyes.
+#[derive(Debug, Clone, Copy)]
+enum Layer {
+    Background,
+    Hat,
+    Accessory,
+}
+
+impl Layer {
+    fn folder(self) -> &'static str {
+        match self {
+            Layer::Background => "backgrounds",
+            Layer::Hat => "hats",
+            Layer::Accessory => "accessories",
+        }
+    }
+}
+
+/// How likely a new dino is to get each optional layer and a different colour.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+struct LayerChances {
+    background: f64,
+    hat: f64,
+    accessory: f64,
+    hue_shift: f64,
+}
+
+impl Default for LayerChances {
+    fn default() -> Self {
+        Self {
+            background: 0.25,
+            hat: 0.1,
+            accessory: 0.15,
+            hue_shift: 0.2,
+        }
+    }
+}
+
+impl LayerChances {
+    fn of(&self, layer: Layer) -> f64 {
+        match layer {
+            Layer::Background => self.background,
+            Layer::Hat => self.hat,
+            Layer::Accessory => self.accessory,
+        }
+    }
+}
+
+/// Rarity weights of the fragments, the higher the weight the more often a fragment is picked.
+#[derive(Debug, Deserialize)]
+struct FragmentManifest {
+    default_weight: u32,
+    #[serde(default)]
+    weights: HashMap<String, u32>,
+    #[serde(default)]
+    chances: LayerChances,
+}
+
+impl Default for FragmentManifest {
+    fn default() -> Self {
+        Self {
+            default_weight: 1,
+            weights: HashMap::new(),
+            chances: LayerChances::default(),
+        }
+    }
+}
+
+impl FragmentManifest {
+    fn weight(&self, file_name: &str) -> u32 {
+        self.weights
+            .get(file_name)
+            .copied()
+            .unwrap_or(self.default_weight)
+    }
 }
 
 #[derive(Debug)]
@@ -36,10 +140,75 @@ struct DinoParts<'a> {
     body: &'a Path,
     mouth: &'a Path,
     eyes: &'a Path,
+    background: Option<&'a Path>,
+    hat: Option<&'a Path>,
+    accessory: Option<&'a Path>,
+    /// Degrees the colours of the dino are rotated by, the background keeps its colours
+    hue: i32,
     name: String,
+    rarity: Rarity,
+    /// The slurped dinos it was bred from
+    parents: Option<[i64; 2]>,
+}
+
+/// How rare a dino's combination of parts is, stored as its level in the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, poise::ChoiceParameter)]
+enum Rarity {
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+impl Rarity {
+    const ALL: [Rarity; 5] = [
+        Rarity::Common,
+        Rarity::Uncommon,
+        Rarity::Rare,
+        Rarity::Epic,
+        Rarity::Legendary,
+    ];
+
+    /// The odds are how many times less likely the parts are to be picked than average ones.
+    fn from_odds(odds: f64) -> Self {
+        match odds {
+            o if o < 2.0 => Rarity::Common,
+            o if o < 4.0 => Rarity::Uncommon,
+            o if o < 8.0 => Rarity::Rare,
+            o if o < 16.0 => Rarity::Epic,
+            _ => Rarity::Legendary,
+        }
+    }
+
+    fn from_level(level: i64) -> Self {
+        usize::try_from(level)
+            .ok()
+            .and_then(|level| Self::ALL.get(level))
+            .copied()
+            .unwrap_or(Rarity::Common)
+    }
+
+    fn level(self) -> i64 {
+        self as i64
+    }
+}
+
+impl Display for Rarity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Rarity::Common => "Common",
+            Rarity::Uncommon => "Uncommon",
+            Rarity::Rare => "Rare",
+            Rarity::Epic => "Epic",
+            Rarity::Legendary => "Legendary",
+        };
+        write!(f, "{name}")
+    }
 }
 
 const FRAGMENT_PATH: &str = "./assets/dino/fragments";
+const FRAGMENT_MANIFEST_PATH: &str = "./assets/dino/fragments.json";
 pub const OUTPUT_PATH: &str = "./assets/dino/complete";
 
 static DINO_FRAGMENTS: OnceLock<Fragments> = OnceLock::new();
@@ -49,6 +218,10 @@ const COLUMN_MARGIN: u32 = 2;
 const ROW_MARGIN: u32 = 2;
 
 const MAX_GENERATION_ATTEMPTS: usize = 20;
+const HUE_SHIFT_STEP: i32 = 30;
+/// The chance for each part of a bred dino to be random instead of coming from a parent
+const MUTATION_CHANCE: f64 = 0.1;
+const UNKNOWN_ANCESTOR: &str = "???";
 const MAX_FAILED_HATCHES: i64 = 3;
 const HATCH_FAILS_TEXT: &[&str; 3] = &["1st", "2nd", "3rd"];
 const MAX_DINO_WORTH_EXPONENT: f64 = 30.0;
@@ -57,11 +230,18 @@ pub const COVET_BUTTON: &str = "dino-covet";
 pub const SHUN_BUTTON: &str = "dino-shun";
 pub const FAVOURITE_BUTTON: &str = "dino-favourite";
 
-pub fn setup_dinos() -> Result<()> {
+pub async fn setup_dinos(database: &SqlitePool) -> Result<()> {
     let fragments_dir =
         std::fs::read_dir(FRAGMENT_PATH).context("Failed to read dino fragment directory")?;
 
-    let mut fragments = Fragments::default();
+    let manifest = read_fragment_manifest()?;
+    let mut fragments = Fragments {
+        backgrounds: read_layer_fragments(Layer::Background, &manifest)?,
+        hats: read_layer_fragments(Layer::Hat, &manifest)?,
+        accessories: read_layer_fragments(Layer::Accessory, &manifest)?,
+        chances: manifest.chances,
+        ..Default::default()
+    };
 
     for entry in fragments_dir {
         let Ok(entry) = entry else {
@@ -71,25 +251,131 @@ pub fn setup_dinos() -> Result<()> {
             continue;
         }
 
-        if let Some(file_stem) = entry.path().file_stem() {
-            match file_stem.to_str() {
-                Some(s) if s.ends_with("_b") => fragments.bodies.push(entry.path()),
-                Some(s) if s.ends_with("_m") => fragments.mouths.push(entry.path()),
-                Some(s) if s.ends_with("_e") => fragments.eyes.push(entry.path()),
-                _ => {}
-            }
-        }
+        let path = entry.path();
+        let (Some(file_stem), Some(file_name)) = (
+            path.file_stem().and_then(|s| s.to_str()),
+            path.file_name().and_then(|s| s.to_str()),
+        ) else {
+            continue;
+        };
+
+        let kind = match file_stem {
+            s if s.ends_with("_b") => &mut fragments.bodies,
+            s if s.ends_with("_m") => &mut fragments.mouths,
+            s if s.ends_with("_e") => &mut fragments.eyes,
+            _ => continue,
+        };
+        kind.push(Fragment {
+            weight: manifest.weight(file_name),
+            path,
+        });
     }
 
+    update_rarities(database, &fragments)
+        .await
+        .context("Failed to update the rarity of existing dinos")?;
     DINO_FRAGMENTS.set(fragments).unwrap();
 
     Ok(())
 }
 
+/// Work out the rarity of every dino again, for dinos from before rarities existed and for
+/// when the weights in the manifest change.
+async fn update_rarities(database: &SqlitePool, fragments: &Fragments) -> Result<()> {
+    let dinos = sqlx::query!("SELECT id, body, mouth, eyes, rarity FROM Dino")
+        .fetch_all(database)
+        .await?;
+
+    let mut transaction = database.begin().await?;
+    for dino in dinos {
+        // Dinos with fragments that were removed keep the rarity they had
+        let (Some(body), Some(mouth), Some(eyes)) = (
+            find_fragment(&fragments.bodies, &dino.body),
+            find_fragment(&fragments.mouths, &dino.mouth),
+            find_fragment(&fragments.eyes, &dino.eyes),
+        ) else {
+            continue;
+        };
+
+        let rarity = fragments.rarity(body, mouth, eyes).level();
+        if rarity != dino.rarity {
+            sqlx::query!("UPDATE Dino SET rarity = ? WHERE id = ?", rarity, dino.id)
+                .execute(&mut *transaction)
+                .await?;
+        }
+    }
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+/// Fragments that aren't in the manifest use its default weight, without a manifest every
+/// fragment is as likely as any other.
+fn read_fragment_manifest() -> Result<FragmentManifest> {
+    let path = Path::new(FRAGMENT_MANIFEST_PATH);
+    if !path.exists() {
+        return Ok(FragmentManifest::default());
+    }
+
+    let manifest = std::fs::read_to_string(path).context("Failed to read fragment manifest")?;
+    let manifest: FragmentManifest =
+        serde_json::from_str(&manifest).context("Failed to parse fragment manifest")?;
+
+    if manifest.default_weight == 0 || manifest.weights.values().any(|&w| w == 0) {
+        bail!("Fragment weights need to be above 0");
+    }
+
+    let chances = manifest.chances;
+    let all_chances = [
+        chances.background,
+        chances.hat,
+        chances.accessory,
+        chances.hue_shift,
+    ];
+    if all_chances.iter().any(|c| !(0.0..=1.0).contains(c)) {
+        bail!("Layer chances need to be between 0 and 1");
+    }
+
+    Ok(manifest)
+}
+
+/// Every image in the layer's folder, a layer without a folder is never picked.
+fn read_layer_fragments(layer: Layer, manifest: &FragmentManifest) -> Result<Vec<Fragment>> {
+    let folder = Path::new(FRAGMENT_PATH).join(layer.folder());
+    if !folder.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut fragments = Vec::new();
+    for entry in std::fs::read_dir(folder).context("Failed to read dino layer directory")? {
+        let path = entry?.path();
+        let is_image = path.extension().is_some_and(|e| e == "png");
+        if let (true, Some(file_name)) = (is_image, path.file_name().and_then(|s| s.to_str())) {
+            fragments.push(Fragment {
+                weight: manifest.weight(file_name),
+                path,
+            });
+        }
+    }
+
+    Ok(fragments)
+}
+
 #[poise::command(
     slash_command,
     guild_only,
-    subcommands("hatch", "collection", "rename", "view", "gift", "slurp", "slurpening")
+    subcommands(
+        "hatch",
+        "collection",
+        "rename",
+        "view",
+        "history",
+        "gift",
+        "trade",
+        "market",
+        "slurp",
+        "slurpening"
+    )
 )]
 pub async fn dino(_ctx: Context<'_>) -> Result<()> {
     Ok(())
@@ -122,9 +408,8 @@ impl Timings {
         }
     }
 
-    fn slurp(user_record: &UserRecord) -> Self {
+    fn slurp(user_record: &UserRecord, slurp_cooldown_duration: TimeDelta) -> Self {
         let attempt = Utc::now().naive_utc();
-        let slurp_cooldown_duration = DINO_SLURP_COOLDOWN;
         let time_until_next_slurp = user_record.last_slurp + slurp_cooldown_duration;
 
         Timings {
@@ -135,9 +420,8 @@ impl Timings {
         }
     }
 
-    fn gift(user_record: &UserRecord) -> Self {
+    fn gift(user_record: &UserRecord, gifting_cooldown_duration: TimeDelta) -> Self {
         let attempt = Utc::now().naive_utc();
-        let gifting_cooldown_duration = DINO_GIFTING_COOLDOWN;
         let time_until_next_gift = user_record.last_gifting + gifting_cooldown_duration;
 
         Timings {
@@ -206,7 +490,7 @@ async fn hatch(ctx: Context<'_>) -> Result<()> {
         return Ok(());
     }
 
-    let Some(parts) = generate_dino(db).await? else {
+    let Some(parts) = generate_dino(db, None).await? else {
         let msg =
             "I tried really hard but i wasn't able to make a unique dino for you. Sorry... :'(";
         return bail_reply(ctx, msg).await;
@@ -217,6 +501,14 @@ async fn hatch(ctx: Context<'_>) -> Result<()> {
     let mut transaction = ctx.data().database.begin().await?;
 
     let dino = insert_dino(&mut transaction, &user.id, &parts, &image_path, None).await?;
+    record_ownership(
+        &mut transaction,
+        dino.id,
+        None,
+        &user.id,
+        OwnershipKind::Hatch,
+    )
+    .await?;
     update_last_user_action(&mut transaction, &user.id, UserAction::Hatch(0)).await?;
 
     let author_name = get_name(&ctx, author).await;
@@ -227,6 +519,7 @@ async fn hatch(ctx: Context<'_>) -> Result<()> {
         &avatar_url(author),
         &image_path,
         user.timings.attempt,
+        None,
     )
     .await?;
 
@@ -243,16 +536,19 @@ async fn collection(
     ctx: Context<'_>,
     #[description = "The user's whose collection you want to view"] user: Option<User>,
     #[description = "The type of collection you want to view"] kind: Option<CollectionKind>,
+    #[description = "Only show dinos of this rarity"] rarity: Option<Rarity>,
+    #[description = "The order the dinos are shown in"] order: Option<CollectionOrder>,
     #[description = "Whether the message will be shown to everyone or not"] silent: Option<bool>,
 ) -> Result<()> {
     let silent = silent.unwrap_or(true);
     let kind = kind.unwrap_or(CollectionKind::All);
+    let order = order.unwrap_or(CollectionOrder::Oldest);
 
     let user_is_author = user.is_none();
     let user = user.as_ref().unwrap_or_else(|| ctx.author());
 
     let db = &ctx.data().database;
-    let dino_collection = fetch_collection(db, &user.id.to_string(), kind).await?;
+    let dino_collection = fetch_collection(db, &user.id.to_string(), kind, rarity, order).await?;
 
     if dino_collection.dinos.is_empty() {
         let content = match user_is_author {
@@ -360,6 +656,9 @@ async fn view(
         }
     };
 
+    let mut conn = ctx.data().database.acquire().await?;
+    let family_tree = family_tree(&mut conn, &dino).await?;
+
     send_dino_embed(
         ctx,
         &dino,
@@ -367,6 +666,7 @@ async fn view(
         &user_avatar,
         &image_path,
         dino.created_at,
+        family_tree,
     )
     .await?;
 
@@ -383,7 +683,11 @@ async fn gift(
     #[description = "The person who will receive the dino"] recipient: User,
 ) -> Result<()> {
     let user_record = get_user_record(&ctx.data().database, &ctx.author().id.to_string()).await?;
-    let timings = Timings::gift(&user_record);
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected dino commands to be guild only.");
+    let config = ctx.data().guild_config(guild_id).await;
+    let timings = Timings::gift(&user_record, config.dino_gifting_cooldown);
 
     if let Err(e) = timings.ensure_outside_cooldown() {
         return bail_reply(ctx, e.to_string()).await;
@@ -402,9 +706,10 @@ async fn gift(
 
     gift_dino(
         &mut transaction,
-        dino_record.id,
+        &dino_record,
         &author_id,
         &recipient.id.to_string(),
+        OwnershipKind::Gift,
     )
     .await?;
     update_last_user_action(&mut transaction, &author_id, UserAction::Gift).await?;
@@ -438,13 +743,19 @@ async fn slurp(
     #[description = "The second dino to be slurped"]
     #[autocomplete = "autocomplete_owned_dinos"]
     second: String,
+    #[description = "Whether the new dino takes after the slurped ones instead of being random"]
+    breed: Option<bool>,
 ) -> Result<()> {
     if first.trim() == second.trim() {
         return bail_reply(ctx, "You can't slurp the same dino twice, you cheater!").await;
     }
 
     let user_record = get_user_record(&ctx.data().database, &ctx.author().id.to_string()).await?;
-    let timings = Timings::slurp(&user_record);
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected dino commands to be guild only.");
+    let config = ctx.data().guild_config(guild_id).await;
+    let timings = Timings::slurp(&user_record, config.dino_slurp_cooldown);
 
     if let Err(e) = timings.ensure_outside_cooldown() {
         return bail_reply(ctx, e.to_string()).await;
@@ -472,7 +783,10 @@ async fn slurp(
         return bail_reply(ctx, msg).await;
     }
 
-    let parts = generate_dino(&ctx.data().database).await?;
+    let parents = breed
+        .unwrap_or(false)
+        .then_some([&first_dino, &second_dino]);
+    let parts = generate_dino(&ctx.data().database, parents).await?;
     if parts.is_none() {
         let msg =
             "I tried really hard but i wasn't able to make a unique dino for you. Sorry... :'(";
@@ -487,7 +801,16 @@ async fn slurp(
     let image_path = generate_dino_image(&parts)?;
 
     let dino = insert_dino(&mut transaction, &author_id, &parts, &image_path, None).await?;
+    record_ownership(
+        &mut transaction,
+        dino.id,
+        None,
+        &author_id,
+        OwnershipKind::Slurp,
+    )
+    .await?;
     update_last_user_action(&mut transaction, &author_id, UserAction::Slurp).await?;
+    let family_tree = family_tree(&mut transaction, &dino).await?;
 
     let author_name = get_name(&ctx, ctx.author()).await;
     let message = send_dino_embed(
@@ -497,6 +820,7 @@ async fn slurp(
         &avatar_url(ctx.author()),
         &image_path,
         Utc::now().naive_utc(),
+        family_tree,
     )
     .await?;
 
@@ -512,7 +836,11 @@ async fn slurp(
 async fn slurpening(ctx: Context<'_>) -> Result<()> {
     let user_id = ctx.author().id.to_string();
     let user_record = get_user_record(&ctx.data().database, &user_id).await?;
-    let timings = Timings::slurp(&user_record);
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected dino commands to be guild only.");
+    let config = ctx.data().guild_config(guild_id).await;
+    let timings = Timings::slurp(&user_record, config.dino_slurp_cooldown);
 
     if let Err(e) = timings.ensure_outside_cooldown() {
         return bail_reply(ctx, e.to_string()).await;
@@ -579,7 +907,7 @@ async fn slurpening(ctx: Context<'_>) -> Result<()> {
 
         let mut created_dinos = Vec::with_capacity(num_to_create);
         for _ in 0..num_to_create {
-            let Some(parts) = generate_dino(&ctx.data().database).await? else {
+            let Some(parts) = generate_dino(&ctx.data().database, None).await? else {
                 interaction
                     .create_response(
                         ctx,
@@ -600,6 +928,14 @@ async fn slurpening(ctx: Context<'_>) -> Result<()> {
                 Some(&message_link),
             )
             .await?;
+            record_ownership(
+                &mut transaction,
+                inserted_dino.id,
+                None,
+                &user_id,
+                OwnershipKind::Slurp,
+            )
+            .await?;
             created_dinos.push(inserted_dino);
         }
 
@@ -717,11 +1053,13 @@ async fn reassign(
         return bail_reply(ctx, "I couldn't find a dino with that name.").await;
     };
 
+    let mut transaction = db.begin().await?;
     if let Err(e) = gift_dino(
-        db,
-        dino_record.id,
+        &mut transaction,
+        &dino_record,
         &ctx.author().id.to_string(),
         &chatter.user.id.to_string(),
+        OwnershipKind::Reassign,
     )
     .await
     {
@@ -729,6 +1067,7 @@ async fn reassign(
         eprintln!("Failed to reassign {dino} dino to {chatter_name}: {e:?}",);
         return bail_reply(ctx, "Failed to reassign dino.").await;
     };
+    transaction.commit().await?;
 
     ctx.say(format!("{dino} was reassigned to {chatter}"))
         .await?;
@@ -802,15 +1141,18 @@ async fn update_last_user_action(
     Ok(())
 }
 
+/// Pick parts for a new dino that doesn't look like any other, bred dinos take after their
+/// parents.
 async fn generate_dino<'a>(
     executor: impl SqliteExecutor<'_> + Copy,
+    parents: Option<[&DinoRecord; 2]>,
 ) -> Result<Option<DinoParts<'a>>> {
     let mut tries = 0;
 
     let fragments = DINO_FRAGMENTS.get().unwrap();
 
     loop {
-        let mut generated = choose_parts(fragments);
+        let mut generated = choose_parts(fragments, parents);
         let duplicate_parts = are_parts_duplicate(executor, &generated).await?;
 
         if !duplicate_parts {
@@ -840,11 +1182,20 @@ async fn are_parts_duplicate<'a>(
     let body = get_file_name(parts.body);
     let mouth = get_file_name(parts.mouth);
     let eyes = get_file_name(parts.eyes);
+    let background = parts.background.map(get_file_name).unwrap_or_default();
+    let hat = parts.hat.map(get_file_name).unwrap_or_default();
+    let accessory = parts.accessory.map(get_file_name).unwrap_or_default();
     let row = sqlx::query!(
-        "SELECT id FROM Dino WHERE body = ? AND mouth = ? AND eyes = ?",
+        "SELECT id FROM Dino
+        WHERE body = ? AND mouth = ? AND eyes = ?
+        AND background = ? AND hat = ? AND accessory = ? AND hue = ?",
         body,
         mouth,
-        eyes
+        eyes,
+        background,
+        hat,
+        accessory,
+        parts.hue
     )
     .fetch_optional(executor)
     .await?;
@@ -863,32 +1214,130 @@ async fn is_name_duplicate(
     Ok(row.is_some())
 }
 
-fn choose_parts(fragments: &Fragments) -> DinoParts<'_> {
+fn choose_parts<'a>(fragments: &'a Fragments, parents: Option<[&DinoRecord; 2]>) -> DinoParts<'a> {
     let mut rng = thread_rng();
-    let body = fragments
-        .bodies
-        .choose(&mut rng)
+    let body = pick_fragment(&fragments.bodies, inherit(parents, |d| &d.body), &mut rng)
         .expect("Expected to have at least one body");
-    let mouth = fragments
-        .mouths
-        .choose(&mut rng)
+    let mouth = pick_fragment(&fragments.mouths, inherit(parents, |d| &d.mouth), &mut rng)
         .expect("Expected to have at least one mouth");
-    let eyes = fragments
-        .eyes
-        .choose(&mut rng)
+    let eyes = pick_fragment(&fragments.eyes, inherit(parents, |d| &d.eyes), &mut rng)
         .expect("Expected to have at least one set of eyes");
 
+    let chances = fragments.chances;
+    let background = pick_layer(
+        &fragments.backgrounds,
+        chances.of(Layer::Background),
+        inherit(parents, |d| &d.background),
+        &mut rng,
+    );
+    let hat = pick_layer(
+        &fragments.hats,
+        chances.of(Layer::Hat),
+        inherit(parents, |d| &d.hat),
+        &mut rng,
+    );
+    let accessory = pick_layer(
+        &fragments.accessories,
+        chances.of(Layer::Accessory),
+        inherit(parents, |d| &d.accessory),
+        &mut rng,
+    );
+
+    let hue = match parents {
+        Some([a, b]) if !rng.gen_bool(MUTATION_CHANCE) => match rng.gen_bool(0.5) {
+            true => a.hue as i32,
+            false => b.hue as i32,
+        },
+        _ if rng.gen_bool(fragments.chances.hue_shift) => {
+            rng.gen_range(1..360 / HUE_SHIFT_STEP) * HUE_SHIFT_STEP
+        }
+        _ => 0,
+    };
+
     let mut parts = DinoParts {
-        body,
-        mouth,
-        eyes,
+        body: &body.path,
+        mouth: &mouth.path,
+        eyes: &eyes.path,
+        background,
+        hat,
+        accessory,
+        hue,
         name: String::new(),
+        rarity: fragments.rarity(body, mouth, eyes),
+        parents: parents.map(|[a, b]| [a.id, b.id]),
     };
 
     parts.name = generate_dino_name(&parts);
     parts
 }
 
+/// The file names of a part of both parents.
+fn inherit<'p>(
+    parents: Option<[&'p DinoRecord; 2]>,
+    part: impl Fn(&'p DinoRecord) -> &'p str,
+) -> Option<[&'p str; 2]> {
+    parents.map(|[a, b]| [part(a), part(b)])
+}
+
+/// Take the part from one of the parents unless it mutates, or there are no parents.
+fn pick_fragment<'a>(
+    fragments: &'a [Fragment],
+    inherited: Option<[&str; 2]>,
+    rng: &mut impl Rng,
+) -> Option<&'a Fragment> {
+    let inherited = inherited
+        .filter(|_| !rng.gen_bool(MUTATION_CHANCE))
+        .and_then(|names| {
+            let name = names.choose(rng)?;
+            find_fragment(fragments, name)
+        });
+
+    inherited.or_else(|| fragments.choose_weighted(rng, |f| f.weight).ok())
+}
+
+/// Like [`pick_fragment`] but a parent without the layer passes on not having it.
+fn pick_layer<'a>(
+    fragments: &'a [Fragment],
+    chance: f64,
+    inherited: Option<[&str; 2]>,
+    rng: &mut impl Rng,
+) -> Option<&'a Path> {
+    match inherited {
+        Some(names) if !rng.gen_bool(MUTATION_CHANCE) => {
+            let name = names.choose(rng)?;
+            find_fragment(fragments, name).map(|f| f.path.as_path())
+        }
+        _ => choose_layer(fragments, chance, rng),
+    }
+}
+
+fn choose_layer<'a>(
+    fragments: &'a [Fragment],
+    chance: f64,
+    rng: &mut impl Rng,
+) -> Option<&'a Path> {
+    if fragments.is_empty() || !rng.gen_bool(chance) {
+        return None;
+    }
+
+    fragments
+        .choose_weighted(rng, |f| f.weight)
+        .ok()
+        .map(|f| f.path.as_path())
+}
+
+fn find_fragment<'a>(fragments: &'a [Fragment], file_name: &str) -> Option<&'a Fragment> {
+    fragments
+        .iter()
+        .find(|f| get_file_name(&f.path) == file_name)
+}
+
+/// How likely the fragment is to be picked compared to an average one of its kind.
+fn relative_chance(fragments: &[Fragment], fragment: &Fragment) -> f64 {
+    let total: u32 = fragments.iter().map(|f| f.weight).sum();
+    fragment.weight as f64 * fragments.len() as f64 / total as f64
+}
+
 fn get_file_name(path: &Path) -> &str {
     path.file_name().unwrap().to_str().unwrap()
 }
@@ -915,16 +1364,34 @@ fn generate_dino_name(parts: &DinoParts) -> String {
 }
 
 fn generate_dino_image(parts: &DinoParts) -> Result<PathBuf> {
-    let mut body = Reader::open(parts.body)?.decode()?;
-    let mouth = Reader::open(parts.mouth)?.decode()?;
-    let eyes = Reader::open(parts.eyes)?.decode()?;
+    let mut dino = Reader::open(parts.body)?.decode()?;
+
+    let layers = [
+        Some(parts.mouth),
+        Some(parts.eyes),
+        parts.accessory,
+        parts.hat,
+    ];
+    for layer in layers.into_iter().flatten() {
+        overlay(&mut dino, &Reader::open(layer)?.decode()?, 0, 0);
+    }
 
-    overlay(&mut body, &mouth, 0, 0);
-    overlay(&mut body, &eyes, 0, 0);
+    if parts.hue != 0 {
+        huerotate_in_place(&mut dino, parts.hue);
+    }
+
+    let image = match parts.background {
+        Some(background) => {
+            let mut background = Reader::open(background)?.decode()?;
+            overlay(&mut background, &dino, 0, 0);
+            background
+        }
+        None => dino,
+    };
 
     let output_path = Path::new(OUTPUT_PATH);
     let path = output_path.join(&parts.name).with_extension("png");
-    body.save_with_format(&path, image::ImageFormat::Png)?;
+    image.save_with_format(&path, image::ImageFormat::Png)?;
 
     Ok(path)
 }
@@ -933,11 +1400,21 @@ pub fn get_dino_image_path(dino: &DinoRecord) -> Result<PathBuf> {
     let path = Path::new(OUTPUT_PATH).join(&dino.filename);
 
     if !path.exists() {
+        let background = layer_path(Layer::Background, &dino.background);
+        let hat = layer_path(Layer::Hat, &dino.hat);
+        let accessory = layer_path(Layer::Accessory, &dino.accessory);
+
         let parts = DinoParts {
             body: &Path::new(FRAGMENT_PATH).join(&dino.body),
             mouth: &Path::new(FRAGMENT_PATH).join(&dino.mouth),
             eyes: &Path::new(FRAGMENT_PATH).join(&dino.eyes),
+            background: background.as_deref(),
+            hat: hat.as_deref(),
+            accessory: accessory.as_deref(),
+            hue: dino.hue as i32,
             name: dino.name.clone(),
+            rarity: Rarity::from_level(dino.rarity),
+            parents: None,
         };
 
         return generate_dino_image(&parts);
@@ -946,6 +1423,15 @@ pub fn get_dino_image_path(dino: &DinoRecord) -> Result<PathBuf> {
     Ok(path)
 }
 
+/// Layers are stored as an empty file name when the dino doesn't have them.
+fn layer_path(layer: Layer, file_name: &str) -> Option<PathBuf> {
+    (!file_name.is_empty()).then(|| {
+        Path::new(FRAGMENT_PATH)
+            .join(layer.folder())
+            .join(file_name)
+    })
+}
+
 fn generate_dino_collection_image(collection: &[DinoRecord]) -> Result<Vec<u8>> {
     let columns = (collection.len() as f32).sqrt().ceil() as u32;
     let rows = (collection.len() as f32 / columns as f32).ceil() as u32;
@@ -1001,8 +1487,13 @@ async fn insert_dino<'a>(
     let body = get_file_name(parts.body);
     let mouth = get_file_name(parts.mouth);
     let eyes = get_file_name(parts.eyes);
+    let background = parts.background.map(get_file_name).unwrap_or_default();
+    let hat = parts.hat.map(get_file_name).unwrap_or_default();
+    let accessory = parts.accessory.map(get_file_name).unwrap_or_default();
     let file_name = get_file_name(file_path);
     let message_link = message_link.unwrap_or_default();
+    let rarity = parts.rarity.level();
+    let [parent_a, parent_b] = parts.parents.map_or([None, None], |p| p.map(Some));
 
     // NOTE: `query_as!` mistakenly interprets all string type fields as
     // nullable strings (when every field is marked NOT NULL), using
@@ -1010,8 +1501,9 @@ async fn insert_dino<'a>(
     let row = sqlx::query_as_unchecked!(
         DinoRecord,
         r#"INSERT INTO Dino
-        (owner_id, name, filename, created_at, body, mouth, eyes, hatch_message)
-        VALUES (?, ?, ?, datetime('now'), ?, ?, ?, ?)
+        (owner_id, name, filename, created_at, body, mouth, eyes, hatch_message, rarity,
+            background, hat, accessory, hue, parent_a, parent_b)
+        VALUES (?, ?, ?, datetime('now'), ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         RETURNING *"#,
         user_id,
         parts.name,
@@ -1019,7 +1511,14 @@ async fn insert_dino<'a>(
         body,
         mouth,
         eyes,
-        message_link
+        message_link,
+        rarity,
+        background,
+        hat,
+        accessory,
+        parts.hue,
+        parent_a,
+        parent_b
     )
     .fetch_one(executor)
     .await?;
@@ -1060,6 +1559,15 @@ pub struct DinoRecord {
     body: String,
     mouth: String,
     eyes: String,
+    rarity: i64,
+
+    background: String,
+    hat: String,
+    accessory: String,
+    hue: i64,
+
+    parent_a: Option<i64>,
+    parent_b: Option<i64>,
 }
 
 struct DinoCollection {
@@ -1099,6 +1607,23 @@ impl DinoCollection {
     }
 }
 
+#[derive(poise::ChoiceParameter)]
+enum CollectionOrder {
+    Oldest,
+    Newest,
+    Rarest,
+}
+
+impl CollectionOrder {
+    fn to_order_by(&self) -> &'static str {
+        match self {
+            CollectionOrder::Oldest => " ORDER BY Dino.id ",
+            CollectionOrder::Newest => " ORDER BY Dino.id DESC ",
+            CollectionOrder::Rarest => " ORDER BY Dino.rarity DESC, Dino.id ",
+        }
+    }
+}
+
 #[derive(poise::ChoiceParameter)]
 enum CollectionKind {
     All,
@@ -1133,6 +1658,8 @@ async fn fetch_collection(
     executor: impl SqliteExecutor<'_> + Copy,
     user_id: &str,
     kind: CollectionKind,
+    rarity: Option<Rarity>,
+    order: CollectionOrder,
 ) -> Result<DinoCollection> {
     // NOTE: query gets reset to whatever was passed into new so I initialized
     // it to an empty string
@@ -1143,6 +1670,8 @@ async fn fetch_collection(
 
     query.push("SELECT * FROM Dino ");
     kind.push_to_query(&mut query, user_id);
+    push_rarity_filter(&mut query, rarity);
+    query.push(order.to_order_by());
     query.push("LIMIT 25");
 
     let dinos: Vec<DinoRecord> = query.build_query_as().fetch_all(executor).await?;
@@ -1151,6 +1680,7 @@ async fn fetch_collection(
     // FIXME: there's probably a better way to get this but this will do for now
     query.push("SELECT COUNT(*), TOTAL(owners) FROM Dino ");
     kind.push_to_query(&mut query, user_id);
+    push_rarity_filter(&mut query, rarity);
 
     let row = query.build().fetch_one(executor).await?;
     let dino_count = row.get(0);
@@ -1163,6 +1693,13 @@ async fn fetch_collection(
     })
 }
 
+fn push_rarity_filter(query: &mut QueryBuilder<'_, Sqlite>, rarity: Option<Rarity>) {
+    if let Some(rarity) = rarity {
+        query.push(" AND Dino.rarity = ");
+        query.push_bind(rarity.level());
+    }
+}
+
 pub async fn get_dino_record(
     executor: impl SqliteExecutor<'_>,
     dino_name: &str,
@@ -1193,6 +1730,7 @@ async fn send_dino_embed(
     owner_avatar: &str,
     image_path: &Path,
     created_at: NaiveDateTime,
+    family_tree: Option<String>,
 ) -> Result<String> {
     // let mut row = CreateActionRow::default();
     let covet = CreateButton::new(format!("{COVET_BUTTON}:{}", dino.id))
@@ -1209,13 +1747,14 @@ async fn send_dino_embed(
         .style(ButtonStyle::Secondary);
 
     let image_name = get_file_name(image_path);
-    let embed = CreateEmbed::default()
+    let mut embed = CreateEmbed::default()
         .colour(0x66ff99)
         .author(CreateEmbedAuthor::new(owner_name).icon_url(owner_avatar))
         .title(&dino.name)
         .description(format!(
-            "**Created:** <t:{}>",
-            created_at.and_utc().timestamp()
+            "**Created:** <t:{}>\n**Rarity:** {}",
+            created_at.and_utc().timestamp(),
+            Rarity::from_level(dino.rarity)
         ))
         .footer(CreateEmbedFooter::new(format!(
             "{} is worth {} Dino Bucks!\nHotness Rating: {}",
@@ -1224,6 +1763,9 @@ async fn send_dino_embed(
             quirkify_hotness(dino.hotness)
         )))
         .attachment(image_name);
+    if let Some(family_tree) = family_tree {
+        embed = embed.field("Family tree", family_tree, false);
+    }
 
     let reply_handle = ctx
         .send(
@@ -1240,33 +1782,41 @@ async fn send_dino_embed(
 }
 
 async fn gift_dino(
-    executor: impl SqliteExecutor<'_>,
-    dino_id: i64,
+    conn: &mut SqliteConnection,
+    dino: &DinoRecord,
     gifter_id: &str,
     recipient_id: &str,
+    kind: OwnershipKind,
 ) -> Result<()> {
     sqlx::query!(
         r#"INSERT OR IGNORE INTO DinoUser (id) VALUES (?);
         INSERT INTO DinoTransactions (dino_id, user_id, gifter_id, type)
         VALUES (?, ?, ?, 'GIFT');
-        UPDATE Dino SET owner_id = ?, owners = owners + 1 WHERE id = ?"#,
+        UPDATE Dino SET owner_id = ? WHERE id = ?"#,
         recipient_id,
-        dino_id,
+        dino.id,
         recipient_id,
         gifter_id,
         recipient_id,
-        dino_id,
+        dino.id,
     )
-    .execute(executor)
+    .execute(&mut *conn)
     .await?;
 
-    Ok(())
+    record_ownership(conn, dino.id, Some(&dino.owner_id), recipient_id, kind).await
 }
 
+/// Slurp a dino, it's remembered as an ancestor for the family trees of bred dinos.
 async fn delete_dino(executor: impl SqliteExecutor<'_>, dino_id: i64) -> Result<()> {
-    let row = sqlx::query!("DELETE FROM Dino WHERE id = ? RETURNING filename", dino_id)
-        .fetch_one(executor)
-        .await?;
+    let row = sqlx::query!(
+        r#"INSERT INTO DinoAncestor (id, name, parent_a, parent_b)
+        SELECT id, name, parent_a, parent_b FROM Dino WHERE id = ?;
+        DELETE FROM Dino WHERE id = ? RETURNING filename"#,
+        dino_id,
+        dino_id
+    )
+    .fetch_one(executor)
+    .await?;
 
     let file_path = Path::new(OUTPUT_PATH).join(row.filename);
     if file_path.exists() {
@@ -1276,6 +1826,84 @@ async fn delete_dino(executor: impl SqliteExecutor<'_>, dino_id: i64) -> Result<
     Ok(())
 }
 
+struct Ancestor {
+    name: String,
+    parent_a: Option<i64>,
+    parent_b: Option<i64>,
+}
+
+/// A dino that's still around or was slurped.
+async fn fetch_ancestor(conn: &mut SqliteConnection, id: i64) -> Result<Option<Ancestor>> {
+    let dino = sqlx::query_as!(
+        Ancestor,
+        "SELECT name, parent_a, parent_b FROM Dino WHERE id = ?",
+        id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    if dino.is_some() {
+        return Ok(dino);
+    }
+
+    let ancestor = sqlx::query_as!(
+        Ancestor,
+        "SELECT name, parent_a, parent_b FROM DinoAncestor WHERE id = ?",
+        id
+    )
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(ancestor)
+}
+
+/// The parents and grandparents of a bred dino.
+async fn family_tree(conn: &mut SqliteConnection, dino: &DinoRecord) -> Result<Option<String>> {
+    let (Some(parent_a), Some(parent_b)) = (dino.parent_a, dino.parent_b) else {
+        return Ok(None);
+    };
+
+    let mut parents = Vec::with_capacity(2);
+    for id in [parent_a, parent_b] {
+        let Some(parent) = fetch_ancestor(conn, id).await? else {
+            parents.push((UNKNOWN_ANCESTOR.to_string(), None));
+            continue;
+        };
+
+        let grandparents = match (parent.parent_a, parent.parent_b) {
+            (Some(a), Some(b)) => {
+                let mut names = [a, b].map(|_| UNKNOWN_ANCESTOR.to_string());
+                for (name, id) in names.iter_mut().zip([a, b]) {
+                    if let Some(grandparent) = fetch_ancestor(conn, id).await? {
+                        *name = grandparent.name;
+                    }
+                }
+                Some(names)
+            }
+            _ => None,
+        };
+        parents.push((parent.name, grandparents));
+    }
+
+    Ok(Some(render_family_tree(&parents)))
+}
+
+fn render_family_tree(parents: &[(String, Option<[String; 2]>)]) -> String {
+    let mut lines = Vec::new();
+    for (i, (name, grandparents)) in parents.iter().enumerate() {
+        let last_parent = i + 1 == parents.len();
+        let branch = if last_parent { '└' } else { '├' };
+        lines.push(format!("{branch}─ {name}"));
+
+        let trunk = if last_parent { ' ' } else { '│' };
+        for (j, grandparent) in grandparents.iter().flatten().enumerate() {
+            let branch = if j == 1 { '└' } else { '├' };
+            lines.push(format!("{trunk}  {branch}─ {grandparent}"));
+        }
+    }
+
+    format!("```\n{}\n```", lines.join("\n"))
+}
+
 async fn get_non_favourites(
     executor: impl SqliteExecutor<'_>,
     user_id: &str,
@@ -1296,7 +1924,7 @@ async fn get_non_favourites(
     Ok(rows)
 }
 
-async fn autocomplete_owned_dinos<'a>(
+pub async fn autocomplete_owned_dinos<'a>(
     ctx: Context<'a>,
     partial: &'a str,
 ) -> impl Iterator<Item = String> + 'a {
@@ -1318,7 +1946,7 @@ async fn autocomplete_owned_dinos<'a>(
     suggestions.into_iter().map(|r| r.name)
 }
 
-async fn autocomplete_all_dinos<'a>(
+pub async fn autocomplete_all_dinos<'a>(
     ctx: Context<'a>,
     partial: &'a str,
 ) -> impl Iterator<Item = String> + 'a {
@@ -1339,7 +1967,8 @@ async fn roll_to_hatch(ctx: Context<'_>) -> Result<i64> {
     let mut hatch_roll = pick_best_x_dice_rolls(4, 1, 1, None) as i64;
 
     if let Some(guild_id) = ctx.guild_id() {
-        if ctx.author().has_role(ctx, guild_id, SUB_ROLE).await? {
+        let sub_role = ctx.data().guild_config(guild_id).await.sub_role;
+        if ctx.author().has_role(ctx, guild_id, sub_role).await? {
             hatch_roll = hatch_roll.max(pick_best_x_dice_rolls(4, 1, 1, None) as i64);
         }
     }
@@ -1386,3 +2015,67 @@ pub fn quirkify_worth(previous_owners: i64) -> String {
 
     format!("{:.3}", 2.0_f64.powf(exponent))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fragment(weight: u32) -> Fragment {
+        Fragment {
+            path: PathBuf::new(),
+            weight,
+        }
+    }
+
+    #[test]
+    fn rarer_fragments_have_lower_chances() {
+        let fragments = [fragment(10), fragment(10), fragment(1)];
+        let total = 21.0;
+
+        assert_eq!(relative_chance(&fragments, &fragments[0]), 30.0 / total);
+        assert_eq!(relative_chance(&fragments, &fragments[2]), 3.0 / total);
+    }
+
+    #[test]
+    fn layers_are_only_picked_when_they_can_be() {
+        let mut rng = thread_rng();
+        let fragments = [fragment(1)];
+
+        assert!(choose_layer(&[], 1.0, &mut rng).is_none());
+        assert!(choose_layer(&fragments, 0.0, &mut rng).is_none());
+        assert!(choose_layer(&fragments, 1.0, &mut rng).is_some());
+    }
+
+    #[test]
+    fn family_trees_show_two_generations() {
+        let parents = [
+            (
+                "rexHi".to_string(),
+                Some(["raxOwO".to_string(), "rexK".to_string()]),
+            ),
+            ("rexPog".to_string(), None),
+        ];
+
+        assert_eq!(
+            render_family_tree(&parents),
+            "```\n├─ rexHi\n│  ├─ raxOwO\n│  └─ rexK\n└─ rexPog\n```"
+        );
+    }
+
+    #[test]
+    fn odds_map_to_tiers() {
+        assert_eq!(Rarity::from_odds(1.0), Rarity::Common);
+        assert_eq!(Rarity::from_odds(3.0), Rarity::Uncommon);
+        assert_eq!(Rarity::from_odds(10.0), Rarity::Epic);
+        assert_eq!(Rarity::from_odds(1000.0), Rarity::Legendary);
+    }
+
+    #[test]
+    fn rarity_levels_roundtrip() {
+        for rarity in Rarity::ALL {
+            assert_eq!(Rarity::from_level(rarity.level()), rarity);
+        }
+        assert_eq!(Rarity::from_level(-1), Rarity::Common);
+        assert_eq!(Rarity::from_level(99), Rarity::Common);
+    }
+}
@@ -0,0 +1,160 @@
+use chrono::Utc;
+use poise::serenity_prelude::{CreateEmbed, CreateEmbedFooter};
+use poise::CreateReply;
+use sqlx::SqliteConnection;
+
+use super::{autocomplete_all_dinos, quirkify_worth};
+use crate::common::bail_reply;
+use crate::{Context, Result};
+
+/// Embed descriptions fit about this many entries, older ones get cut off.
+const MAX_HISTORY_ENTRIES: usize = 40;
+
+/// How a dino ended up with one of its owners.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+pub enum OwnershipKind {
+    Hatch,
+    Gift,
+    Trade,
+    Reassign,
+    Slurp,
+}
+
+struct Ownership {
+    owner_id: String,
+    previous_owner_id: Option<String>,
+    kind: OwnershipKind,
+    created_at: i64,
+}
+
+impl Ownership {
+    fn describe(&self) -> String {
+        let owner = format!("<@{}>", self.owner_id);
+        let from = self
+            .previous_owner_id
+            .as_deref()
+            .map(|p| format!(" from <@{p}>"))
+            .unwrap_or_default();
+
+        let event = match self.kind {
+            OwnershipKind::Hatch => format!("Hatched by {owner}"),
+            OwnershipKind::Gift => format!("Gifted to {owner}{from}"),
+            OwnershipKind::Trade => format!("Traded to {owner}{from}"),
+            OwnershipKind::Reassign => format!("Reassigned to {owner}{from}"),
+            OwnershipKind::Slurp => format!("Slurped up by {owner}"),
+        };
+
+        format!("<t:{}:d> {event}", self.created_at)
+    }
+}
+
+/// Keep track of the dino's new owner, its worth is based on how many owners it has had.
+pub async fn record_ownership(
+    conn: &mut SqliteConnection,
+    dino_id: i64,
+    previous_owner_id: Option<&str>,
+    owner_id: &str,
+    kind: OwnershipKind,
+) -> Result<()> {
+    let created_at = Utc::now().timestamp();
+    sqlx::query!(
+        r#"INSERT INTO DinoOwnership (dino_id, owner_id, previous_owner_id, kind, created_at)
+        VALUES (?, ?, ?, ?, ?);
+        UPDATE Dino SET owners = (SELECT COUNT(*) FROM DinoOwnership WHERE dino_id = ?)
+        WHERE id = ?"#,
+        dino_id,
+        owner_id,
+        previous_owner_id,
+        kind,
+        created_at,
+        dino_id,
+        dino_id
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// See everyone who has owned a dino
+#[poise::command(guild_only, slash_command)]
+pub async fn history(
+    ctx: Context<'_>,
+    #[description = "The name of the dino"]
+    #[autocomplete = "autocomplete_all_dinos"]
+    name: String,
+) -> Result<()> {
+    let mut conn = ctx.data().database.acquire().await?;
+
+    let dino = sqlx::query!("SELECT id, name FROM Dino WHERE name = ?", name)
+        .fetch_optional(&mut *conn)
+        .await?;
+    let Some(dino) = dino else {
+        return bail_reply(ctx, "The name of the dino you specified was not found.").await;
+    };
+
+    let history = sqlx::query_as!(
+        Ownership,
+        r#"SELECT owner_id, previous_owner_id, kind AS "kind!: OwnershipKind", created_at
+        FROM DinoOwnership
+        WHERE dino_id = ?
+        ORDER BY id"#,
+        dino.id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    if history.is_empty() {
+        return bail_reply(ctx, format!("Nobody knows where {} came from.", dino.name)).await;
+    }
+
+    let skipped = history.len().saturating_sub(MAX_HISTORY_ENTRIES);
+    let mut lines = history[skipped..]
+        .iter()
+        .map(Ownership::describe)
+        .collect::<Vec<_>>();
+    if skipped > 0 {
+        lines.insert(0, format!("…and {skipped} earlier owners"));
+    }
+
+    let owners = history.len() as i64;
+    let embed = CreateEmbed::default()
+        .colour(0x66ff99)
+        .title(format!("History of {}", dino.name))
+        .description(lines.join("\n"))
+        .footer(CreateEmbedFooter::new(format!(
+            "{owners} owners, {} is worth {} Dino Bucks!",
+            dino.name,
+            quirkify_worth(owners)
+        )));
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ownership(kind: OwnershipKind, previous_owner_id: Option<&str>) -> Ownership {
+        Ownership {
+            owner_id: "2".to_string(),
+            previous_owner_id: previous_owner_id.map(str::to_string),
+            kind,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn describes_where_dinos_came_from() {
+        assert_eq!(
+            ownership(OwnershipKind::Hatch, None).describe(),
+            "<t:0:d> Hatched by <@2>"
+        );
+        assert_eq!(
+            ownership(OwnershipKind::Trade, Some("1")).describe(),
+            "<t:0:d> Traded to <@2> from <@1>"
+        );
+    }
+}
@@ -0,0 +1,553 @@
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use chrono::Utc;
+use poise::futures_util::StreamExt;
+use poise::serenity_prelude::{
+    self as serenity, ButtonStyle, ComponentInteraction, ComponentInteractionCollector,
+    CreateActionRow, CreateButton, CreateEmbed, CreateEmbedFooter, Mentionable, User, UserId,
+};
+use poise::CreateReply;
+use sqlx::{SqliteExecutor, SqlitePool};
+
+use super::history::{record_ownership, OwnershipKind};
+use super::{autocomplete_all_dinos, autocomplete_owned_dinos};
+use crate::common::{
+    bail_reply, ephemeral_reply, ephemeral_text_message, reply_with_buttons, response,
+    text_message, update_response,
+};
+use crate::{Context, Data, Result};
+
+const TRADE_CONFIRM: &str = "trade-confirm";
+const TRADE_CANCEL: &str = "trade-cancel";
+const OFFER_ACCEPT: &str = "market-accept";
+const OFFER_DECLINE: &str = "market-decline";
+
+const TRADE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+const MAX_LISTINGS: i64 = 25;
+
+struct OwnedDino {
+    id: i64,
+    owner_id: String,
+    name: String,
+}
+
+/// Trade one of your dinos for one of another chatter's dinos
+#[poise::command(guild_only, slash_command)]
+pub async fn trade(
+    ctx: Context<'_>,
+    #[description = "The chatter you want to trade with"] partner: User,
+    #[description = "The dino you're giving away"]
+    #[autocomplete = "autocomplete_owned_dinos"]
+    offer: String,
+    #[description = "The dino you want in return"]
+    #[autocomplete = "autocomplete_all_dinos"]
+    request: String,
+) -> Result<()> {
+    let author = ctx.author();
+    if partner.id == author.id || partner.bot {
+        return bail_reply(ctx, "You need someone else to trade with.").await;
+    }
+
+    let db = &ctx.data().database;
+    let offered = match owned_dino(db, &offer, author.id).await? {
+        Ok(dino) => dino,
+        Err(reason) => return bail_reply(ctx, reason).await,
+    };
+    let requested = match owned_dino(db, &request, partner.id).await? {
+        Ok(dino) => dino,
+        Err(reason) => return bail_reply(ctx, reason).await,
+    };
+
+    let buttons = vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(TRADE_CONFIRM)
+            .emoji('🤝')
+            .label("Confirm")
+            .style(ButtonStyle::Success),
+        CreateButton::new(TRADE_CANCEL)
+            .label("Cancel")
+            .style(ButtonStyle::Danger),
+    ])];
+    let traders = [author.id, partner.id];
+    let mut confirmed = BTreeSet::new();
+    let reply_handle = ctx
+        .send(reply_with_buttons(
+            trade_summary(traders, &offered, &requested, &confirmed),
+            buttons,
+        ))
+        .await?;
+    let message = reply_handle.message().await?;
+
+    while let Some(interaction) = ComponentInteractionCollector::new(ctx)
+        .message_id(message.id)
+        .timeout(TRADE_TIMEOUT)
+        .await
+    {
+        if !traders.contains(&interaction.user.id) {
+            let resp = response(ephemeral_text_message("This isn't your trade."));
+            interaction.create_response(ctx, resp).await?;
+            continue;
+        }
+
+        if interaction.data.custom_id == TRADE_CANCEL {
+            let content = format!("{} called off the trade.", interaction.user.mention());
+            let resp = update_response(text_message(content).components(Vec::new()));
+            interaction.create_response(ctx, resp).await?;
+            return Ok(());
+        }
+
+        confirmed.insert(interaction.user.id);
+        if confirmed.len() < traders.len() {
+            let content = trade_summary(traders, &offered, &requested, &confirmed);
+            interaction
+                .create_response(ctx, update_response(text_message(content)))
+                .await?;
+            continue;
+        }
+
+        let content = if swap_dinos(db, &offered, &requested).await? {
+            format!(
+                "{} traded **{}** to {} for **{}**!",
+                author.mention(),
+                offered.name,
+                partner.mention(),
+                requested.name
+            )
+        } else {
+            "One of the dinos changed hands before the trade went through.".to_string()
+        };
+        let resp = update_response(text_message(content).components(Vec::new()));
+        interaction.create_response(ctx, resp).await?;
+
+        return Ok(());
+    }
+
+    reply_handle
+        .edit(
+            ctx,
+            reply_with_buttons("The trade offer expired.", Vec::new()),
+        )
+        .await?;
+
+    Ok(())
+}
+
+fn trade_summary(
+    traders: [UserId; 2],
+    offered: &OwnedDino,
+    requested: &OwnedDino,
+    confirmed: &BTreeSet<UserId>,
+) -> String {
+    let [author, partner] = traders;
+    let status = |id: &UserId| match confirmed.contains(id) {
+        true => "✅",
+        false => "⌛",
+    };
+
+    format!(
+        "{} wants to trade **{}** for {}'s **{}**.\n\
+        Both of you need to confirm.\n\
+        {} {}\n{} {}",
+        author.mention(),
+        offered.name,
+        partner.mention(),
+        requested.name,
+        status(&author),
+        author.mention(),
+        status(&partner),
+        partner.mention()
+    )
+}
+
+#[poise::command(
+    guild_only,
+    slash_command,
+    subcommands("list", "unlist", "browse", "offer")
+)]
+pub async fn market(_ctx: Context<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Put one of your dinos up for trade
+#[poise::command(guild_only, slash_command)]
+async fn list(
+    ctx: Context<'_>,
+    #[description = "The dino you want to trade away"]
+    #[autocomplete = "autocomplete_owned_dinos"]
+    dino: String,
+    #[description = "What you'd like in return"] note: Option<String>,
+) -> Result<()> {
+    let db = &ctx.data().database;
+    let dino = match owned_dino(db, &dino, ctx.author().id).await? {
+        Ok(dino) => dino,
+        Err(reason) => return bail_reply(ctx, reason).await,
+    };
+
+    let created_at = Utc::now().timestamp();
+    sqlx::query!(
+        "INSERT OR REPLACE INTO DinoListing (dino_id, seller_id, note, created_at)
+        VALUES (?, ?, ?, ?)",
+        dino.id,
+        dino.owner_id,
+        note,
+        created_at
+    )
+    .execute(db)
+    .await?;
+
+    ctx.say(format!(
+        "**{}** is up for trade! Use `/dino market offer` to make an offer.",
+        dino.name
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Take one of your dinos off the market
+#[poise::command(guild_only, slash_command)]
+async fn unlist(
+    ctx: Context<'_>,
+    #[description = "The dino you want to keep"]
+    #[autocomplete = "autocomplete_listed_dinos"]
+    dino: String,
+) -> Result<()> {
+    let db = &ctx.data().database;
+    let dino = match owned_dino(db, &dino, ctx.author().id).await? {
+        Ok(dino) => dino,
+        Err(reason) => return bail_reply(ctx, reason).await,
+    };
+
+    let removed = sqlx::query!("DELETE FROM DinoListing WHERE dino_id = ?", dino.id)
+        .execute(db)
+        .await?
+        .rows_affected();
+
+    if removed == 0 {
+        return bail_reply(ctx, format!("**{}** isn't up for trade.", dino.name)).await;
+    }
+
+    ctx.send(ephemeral_reply(format!(
+        "**{}** has been taken off the market.",
+        dino.name
+    )))
+    .await?;
+
+    Ok(())
+}
+
+/// See which dinos are up for trade
+#[poise::command(guild_only, slash_command)]
+async fn browse(ctx: Context<'_>) -> Result<()> {
+    let listings = sqlx::query!(
+        r#"SELECT Dino.name AS "name!", DinoListing.seller_id AS "seller_id!", DinoListing.note
+        FROM DinoListing
+        INNER JOIN Dino ON Dino.id = DinoListing.dino_id AND Dino.owner_id = DinoListing.seller_id
+        ORDER BY DinoListing.created_at DESC
+        LIMIT ?"#,
+        MAX_LISTINGS
+    )
+    .fetch_all(&ctx.data().database)
+    .await?;
+
+    if listings.is_empty() {
+        return bail_reply(ctx, "Nobody is trading any dinos right now.").await;
+    }
+
+    let description = listings
+        .iter()
+        .map(|l| {
+            let note = l
+                .note
+                .as_deref()
+                .map(|n| format!(": {n}"))
+                .unwrap_or_default();
+            format!("**{}** from <@{}>{note}", l.name, l.seller_id)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let embed = CreateEmbed::default()
+        .colour(0xffbf00)
+        .title("Dino market")
+        .description(description)
+        .footer(CreateEmbedFooter::new(
+            "Use /dino market offer to trade for one of them",
+        ));
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+/// Offer one of your dinos for a dino on the market
+#[poise::command(guild_only, slash_command)]
+async fn offer(
+    ctx: Context<'_>,
+    #[description = "The dino you want"]
+    #[autocomplete = "autocomplete_listed_dinos"]
+    listing: String,
+    #[description = "The dino you're giving in return"]
+    #[autocomplete = "autocomplete_owned_dinos"]
+    dino: String,
+) -> Result<()> {
+    let db = &ctx.data().database;
+    let Some(listed) = listed_dino(db, &listing).await? else {
+        return bail_reply(ctx, format!("**{listing}** isn't up for trade.")).await;
+    };
+    if listed.owner_id == ctx.author().id.to_string() {
+        return bail_reply(ctx, "You can't make an offer on your own dino.").await;
+    }
+
+    let offered = match owned_dino(db, &dino, ctx.author().id).await? {
+        Ok(dino) => dino,
+        Err(reason) => return bail_reply(ctx, reason).await,
+    };
+
+    let ids = format!("{}:{}:{}", listed.id, offered.id, ctx.author().id);
+    let buttons = vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("{OFFER_ACCEPT}:{ids}"))
+            .emoji('🤝')
+            .label("Accept")
+            .style(ButtonStyle::Success),
+        CreateButton::new(format!("{OFFER_DECLINE}:{ids}"))
+            .label("Decline")
+            .style(ButtonStyle::Danger),
+    ])];
+    let content = format!(
+        "<@{}>, {} offers **{}** for your **{}**.",
+        listed.owner_id,
+        ctx.author().mention(),
+        offered.name,
+        listed.name
+    );
+
+    ctx.send(reply_with_buttons(content, buttons)).await?;
+
+    Ok(())
+}
+
+/// Market offers stay open until they are answered, even after restarts.
+pub async fn setup_market_collector(ctx: &serenity::Context, user_data: &Data) -> Result<()> {
+    let mut collector = ComponentInteractionCollector::new(ctx)
+        .filter(|f| {
+            let prefix = f.data.custom_id.split(':').next();
+            [OFFER_ACCEPT, OFFER_DECLINE]
+                .iter()
+                .any(|&p| prefix == Some(p))
+        })
+        .stream();
+
+    println!("Setup dino market collector");
+
+    while let Some(interaction) = collector.next().await {
+        if let Err(e) = handle_offer(ctx, &user_data.database, &interaction).await {
+            eprintln!("Error while handling dino market offer: {e}");
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_offer(
+    ctx: &serenity::Context,
+    db: &SqlitePool,
+    interaction: &ComponentInteraction,
+) -> Result<()> {
+    let parts = interaction.data.custom_id.split(':').collect::<Vec<_>>();
+    let ids = (
+        parts.get(1).and_then(|id| id.parse::<i64>().ok()),
+        parts.get(2).and_then(|id| id.parse::<i64>().ok()),
+        parts.get(3).and_then(|id| id.parse::<UserId>().ok()),
+    );
+    let (Some(listed_id), Some(offered_id), Some(offerer)) = ids else {
+        let resp = response(ephemeral_text_message("This offer is broken."));
+        interaction.create_response(ctx, resp).await?;
+        return Ok(());
+    };
+
+    let listed = dino_by_id(db, listed_id).await?;
+    let offered = dino_by_id(db, offered_id).await?;
+    let clicker = interaction.user.id;
+
+    let (Some(listed), Some(offered)) = (listed, offered) else {
+        let content = "One of the dinos in this offer is no longer with us 😔";
+        let resp = update_response(text_message(content).components(Vec::new()));
+        interaction.create_response(ctx, resp).await?;
+        return Ok(());
+    };
+    let seller = listed.owner_id.parse::<UserId>()?;
+
+    if parts[0] == OFFER_DECLINE {
+        let content = match clicker {
+            c if c == seller => format!(
+                "{} declined the offer for **{}**.",
+                c.mention(),
+                listed.name
+            ),
+            c if c == offerer => format!(
+                "{} withdrew their offer for **{}**.",
+                c.mention(),
+                listed.name
+            ),
+            _ => {
+                let resp = response(ephemeral_text_message("This isn't your offer."));
+                interaction.create_response(ctx, resp).await?;
+                return Ok(());
+            }
+        };
+        let resp = update_response(text_message(content).components(Vec::new()));
+        interaction.create_response(ctx, resp).await?;
+        return Ok(());
+    }
+
+    if clicker != seller {
+        let content = format!(
+            "Only the owner of **{}** can accept this offer.",
+            listed.name
+        );
+        let resp = response(ephemeral_text_message(content));
+        interaction.create_response(ctx, resp).await?;
+        return Ok(());
+    }
+
+    let still_listed = listed_dino(db, &listed.name).await?.is_some();
+    let content = if still_listed
+        && offered.owner_id == offerer.to_string()
+        && swap_dinos(db, &listed, &offered).await?
+    {
+        format!(
+            "{} traded **{}** to {} for **{}**!",
+            seller.mention(),
+            listed.name,
+            offerer.mention(),
+            offered.name
+        )
+    } else {
+        "This offer isn't valid anymore.".to_string()
+    };
+
+    let resp = update_response(text_message(content).components(Vec::new()));
+    interaction.create_response(ctx, resp).await?;
+
+    Ok(())
+}
+
+/// Give each dino to the owner of the other one, nothing changes if either has changed hands
+/// since the trade was set up.
+async fn swap_dinos(db: &SqlitePool, first: &OwnedDino, second: &OwnedDino) -> Result<bool> {
+    let mut transaction = db.begin().await?;
+
+    for (dino, recipient) in [(first, &second.owner_id), (second, &first.owner_id)] {
+        let moved = sqlx::query!(
+            "UPDATE Dino SET owner_id = ? WHERE id = ? AND owner_id = ?",
+            recipient,
+            dino.id,
+            dino.owner_id
+        )
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected();
+
+        if moved == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query!(
+            r#"INSERT INTO DinoTransactions (dino_id, user_id, gifter_id, type)
+            VALUES (?, ?, ?, 'TRADE');
+            DELETE FROM DinoListing WHERE dino_id = ?"#,
+            dino.id,
+            recipient,
+            dino.owner_id,
+            dino.id
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        record_ownership(
+            &mut transaction,
+            dino.id,
+            Some(&dino.owner_id),
+            recipient,
+            OwnershipKind::Trade,
+        )
+        .await?;
+    }
+
+    transaction.commit().await?;
+
+    Ok(true)
+}
+
+/// Find a dino by name, making sure it belongs to the user.
+async fn owned_dino(
+    executor: impl SqliteExecutor<'_>,
+    name: &str,
+    owner: UserId,
+) -> Result<std::result::Result<OwnedDino, String>> {
+    let dino = sqlx::query_as!(
+        OwnedDino,
+        "SELECT id, owner_id, name FROM Dino WHERE name = ?",
+        name
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(match dino {
+        None => Err(format!("Could not find a dino named {name}.")),
+        Some(dino) if dino.owner_id != owner.to_string() => {
+            Err(format!("<@{owner}> doesn't own **{}**.", dino.name))
+        }
+        Some(dino) => Ok(dino),
+    })
+}
+
+async fn dino_by_id(executor: impl SqliteExecutor<'_>, id: i64) -> Result<Option<OwnedDino>> {
+    let dino = sqlx::query_as!(
+        OwnedDino,
+        "SELECT id, owner_id, name FROM Dino WHERE id = ?",
+        id
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(dino)
+}
+
+/// A dino that's on the market and still belongs to whoever listed it.
+async fn listed_dino(executor: impl SqliteExecutor<'_>, name: &str) -> Result<Option<OwnedDino>> {
+    let dino = sqlx::query_as!(
+        OwnedDino,
+        "SELECT Dino.id, Dino.owner_id, Dino.name FROM Dino
+        INNER JOIN DinoListing ON Dino.id = DinoListing.dino_id
+        WHERE Dino.name = ? AND Dino.owner_id = DinoListing.seller_id",
+        name
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(dino)
+}
+
+async fn autocomplete_listed_dinos<'a>(
+    ctx: Context<'a>,
+    partial: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+    let partial = format!("%{partial}%");
+
+    let suggestions = sqlx::query!(
+        "SELECT Dino.name FROM Dino
+        INNER JOIN DinoListing ON Dino.id = DinoListing.dino_id
+        WHERE Dino.owner_id = DinoListing.seller_id AND Dino.name LIKE ? LIMIT 5",
+        partial
+    )
+    .fetch_all(&ctx.data().database)
+    .await
+    .unwrap_or_else(|e| {
+        eprintln!("Error while trying to suggest autocomplete for '{partial}': {e}");
+        vec![]
+    });
+
+    suggestions.into_iter().map(|r| r.name)
+}
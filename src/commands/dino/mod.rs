@@ -1,5 +1,8 @@
 mod collectors;
 mod commands;
+mod history;
+mod market;
 
 pub use collectors::*;
 pub use commands::*;
+pub use market::setup_market_collector;
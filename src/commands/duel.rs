@@ -2,11 +2,13 @@ use crate::common::{
     avatar_url, bail_reply, colour, ephemeral_text_message, name, reply_with_buttons, response,
     text_message, update_response, user_name,
 };
-use crate::config::{DEAD_DUEL_COOLDOWN, DRAW_TIMEOUT_DURATION, DUEL_LOSS_COOLDOWN};
+use crate::config::GuildConfig;
 use crate::Context;
 
+use super::modlog::{format_duration, try_record_action, ModerationAction, ModerationSource};
+
 use anyhow::{bail, Context as AnyhowContext, Result};
-use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
 use poise::serenity_prelude::{
     ButtonStyle, CreateActionRow, CreateButton, CreateEmbed, CreateEmbedAuthor, Member, User,
     UserId,
@@ -40,7 +42,12 @@ pub async fn challenge(
         return bail_reply(ctx, "A duel is already in progress").await;
     }
 
-    if let Err(e) = challenger.ensure_outside_cooldown(ctx).await {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /duel challenge to be guild only.");
+    let config = ctx.data().guild_config(guild_id).await;
+
+    if let Err(e) = challenger.ensure_outside_cooldown(ctx, &config).await {
         return bail_reply(ctx, e.to_string()).await;
     }
 
@@ -56,7 +63,7 @@ pub async fn challenge(
 
     // Make sure the in_progress status gets updated even on failure
     IN_PROGRESS.store(true, AtomicOrdering::Release);
-    if let Err(e) = run_duel(ctx, challenger, reply_handle, wager).await {
+    if let Err(e) = run_duel(ctx, &config, challenger, reply_handle, wager).await {
         eprintln!("Failed to run duel to completiton: {e:?}");
     }
     IN_PROGRESS.store(false, AtomicOrdering::Release);
@@ -66,12 +73,13 @@ pub async fn challenge(
 
 async fn run_duel(
     ctx: Context<'_>,
+    config: &GuildConfig,
     challenger: DuelUser,
     reply_handle: ReplyHandle<'_>,
     wager: String,
 ) -> Result<()> {
     let message = reply_handle.message().await?;
-    let opponent = find_opponent(ctx, message.id, challenger.id.get()).await;
+    let opponent = find_opponent(ctx, config, message.id, challenger.id.get()).await;
 
     let Some((interaction, accepter)) = opponent else {
         let duel_timeout_msg = format!("{challenger} failed to find someone to duel.");
@@ -106,14 +114,17 @@ async fn run_duel(
                 .await?;
 
             let timeout_end_time = Utc::now()
-                .checked_add_signed(DRAW_TIMEOUT_DURATION)
+                .checked_add_signed(config.draw_timeout_duration)
                 .unwrap();
             let challenger_member = ctx.author_member().await.map(|m| m.into_owned());
-            timeout_user(ctx, challenger_member, timeout_end_time).await;
-            timeout_user(ctx, interaction.member.clone(), timeout_end_time).await;
+            let duration = Some(config.draw_timeout_duration);
+            timeout_user(ctx, challenger_member, timeout_end_time, duration).await;
+            timeout_user(ctx, interaction.member.clone(), timeout_end_time, duration).await;
 
-            "It's a draw! Now go sit in a corner for 10 mintues and think about your actions..."
-                .into()
+            format!(
+                "It's a draw! Now go sit in a corner for {} and think about your actions...",
+                format_duration(config.draw_timeout_duration.num_seconds())
+            )
         }
     };
 
@@ -132,13 +143,14 @@ async fn run_duel(
 
 async fn find_opponent(
     ctx: Context<'_>,
+    config: &GuildConfig,
     message_id: MessageId,
     challenger_id: u64,
 ) -> Option<(ComponentInteraction, DuelUser)> {
     while let Some(interaction) = ComponentInteractionCollector::new(ctx)
         .message_id(message_id)
         .filter(move |f| f.data.custom_id == "duel-btn")
-        .timeout(DEAD_DUEL_COOLDOWN.to_std().unwrap())
+        .timeout(config.dead_duel_cooldown.to_std().unwrap())
         .await
     {
         // NOTE: responding with an ephemeral message does not trigger the
@@ -159,7 +171,7 @@ async fn find_opponent(
         }
 
         let accepter = DuelUser::from(ctx, &interaction.user).await;
-        if let Err(e) = accepter.ensure_outside_cooldown(ctx).await {
+        if let Err(e) = accepter.ensure_outside_cooldown(ctx, config).await {
             let resp = response(ephemeral_text_message(e.to_string()));
             interaction.create_response(ctx, resp).await.ok()?;
             continue;
@@ -408,7 +420,12 @@ async fn streaks(ctx: Context<'_>) -> Result<()> {
     Ok(())
 }
 
-async fn timeout_user(ctx: Context<'_>, member: Option<Member>, until: DateTime<Utc>) {
+async fn timeout_user(
+    ctx: Context<'_>,
+    member: Option<Member>,
+    until: DateTime<Utc>,
+    duration: Option<TimeDelta>,
+) {
     let Some(mut member) = member else {
         return;
     };
@@ -418,7 +435,20 @@ async fn timeout_user(ctx: Context<'_>, member: Option<Member>, until: DateTime<
         .await
     {
         eprintln!("Failed to timeout {}, reason: {e:?}", member.user.name);
+        return;
     }
+
+    try_record_action(
+        ctx,
+        ModerationAction {
+            actor: ctx.framework().bot_id,
+            target: member.user.id,
+            reason: Some("Drew a duel".to_string()),
+            duration,
+            source: ModerationSource::DuelDraw,
+        },
+    )
+    .await;
 }
 
 fn create_accept_button() -> CreateActionRow {
@@ -447,7 +477,7 @@ impl DuelUser {
         }
     }
 
-    async fn ensure_outside_cooldown(&self, ctx: Context<'_>) -> Result<()> {
+    async fn ensure_outside_cooldown(&self, ctx: Context<'_>, config: &GuildConfig) -> Result<()> {
         let last_loss = match get_last_loss(&ctx.data().database, &self.string_id).await {
             Ok(last_loss) => last_loss,
             Err(e) => {
@@ -456,7 +486,7 @@ impl DuelUser {
             }
         };
 
-        let time_until_duel = (last_loss + DUEL_LOSS_COOLDOWN).and_utc();
+        let time_until_duel = (last_loss + config.duel_loss_cooldown).and_utc();
         if time_until_duel > Utc::now() {
             bail!(
                 "{self} you have recently lost a duel. Please try again <t:{}:R>.",
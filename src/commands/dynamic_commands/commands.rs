@@ -0,0 +1,1144 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail};
+use chrono::{NaiveDateTime, Utc};
+use poise::serenity_prelude::{
+    Attachment, ButtonStyle, ChannelId, CommandDataOptionValue, CommandInteraction,
+    CommandOptionType, ComponentInteractionCollector, Context as SerenityContext, CreateActionRow,
+    CreateAllowedMentions, CreateAttachment, CreateButton, CreateCommand, CreateCommandOption,
+    CreateEmbed, CreateEmbedFooter, CreateInteractionResponseMessage, GuildId, Role, RoleId,
+    UserId,
+};
+use poise::{ChoiceParameter, CreateReply};
+use rand::seq::IteratorRandom;
+use serde::{Deserialize, Serialize};
+use serenity::all::Interaction;
+use sqlx::SqliteExecutor;
+
+use super::images::{remove_command_image, save_command_image};
+use super::restrictions::{CommandRestrictions, LastUses};
+use super::template::{render, uses_variable, TemplateContext, ARG_OPTION, TARGET_OPTION};
+use crate::{
+    commands::{colors::to_color, DEFAULT_COMMANDS},
+    common::{
+        bail_reply, embed_message, ephemeral_reply, ephemeral_text_message, none_to_clear,
+        response, text_message, update_response,
+    },
+    Context, Data, Result,
+};
+
+#[derive(
+    Debug, sqlx::Type, poise::ChoiceParameter, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum CommandKind {
+    Static,
+    Choice,
+    Embed,
+    Image,
+}
+
+pub struct CommandInfo {
+    pub kind: CommandKind,
+    pub content: String,
+    pub description: Option<String>,
+    pub title: Option<String>,
+    pub colour: Option<i64>,
+    /// A URL for embed commands, the path on disk for image commands
+    pub image: Option<String>,
+    pub restrictions: CommandRestrictions,
+    pub last_uses: LastUses,
+}
+
+impl CommandInfo {
+    fn uses_variable(&self, name: &str) -> bool {
+        uses_variable(&self.content, name)
+            || self
+                .title
+                .as_deref()
+                .is_some_and(|t| uses_variable(t, name))
+    }
+
+    fn image_path(&self) -> Option<&str> {
+        match self.kind {
+            CommandKind::Image => self.image.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+const MAX_REPORTED_LINES: usize = 15;
+
+pub type SimpleCommands = HashMap<i64, HashMap<String, CommandInfo>>;
+
+const COMMANDS_PER_PAGE: usize = 10;
+const PAGE_BUTTON: &str = "commands-page";
+const PAGE_TIMEOUT: Duration = Duration::from_secs(120);
+
+struct ListedCommand {
+    name: String,
+    kind: CommandKind,
+    description: Option<String>,
+    uses: i64,
+    last_used: Option<NaiveDateTime>,
+}
+
+impl ListedCommand {
+    fn line(&self) -> String {
+        let description = self
+            .description
+            .as_deref()
+            .map(|d| format!(": {d}"))
+            .unwrap_or_default();
+        let last_used = match self.last_used {
+            Some(last_used) => format!(", last <t:{}:R>", last_used.and_utc().timestamp()),
+            None => String::new(),
+        };
+
+        format!(
+            "**/{}** ({}){description}\nUsed {} times{last_used}",
+            self.name,
+            self.kind.name(),
+            self.uses
+        )
+    }
+}
+
+/// How a command looks in an export file. Image commands are left out since their
+/// image only exists on this bot's disk.
+#[derive(Serialize, Deserialize)]
+struct ExportedCommand {
+    name: String,
+    kind: CommandKind,
+    content: String,
+    description: Option<String>,
+    title: Option<String>,
+    colour: Option<i64>,
+    image_url: Option<String>,
+    #[serde(default)]
+    cooldown: i64,
+    #[serde(default)]
+    user_cooldown: i64,
+    #[serde(default)]
+    allowed_channels: Vec<ChannelId>,
+    required_role: Option<RoleId>,
+}
+
+impl ExportedCommand {
+    fn new(name: &str, command: &CommandInfo) -> Self {
+        Self {
+            name: name.to_owned(),
+            kind: command.kind,
+            content: command.content.clone(),
+            description: command.description.clone(),
+            title: command.title.clone(),
+            colour: command.colour,
+            image_url: command.image.clone(),
+            cooldown: command.restrictions.cooldown,
+            user_cooldown: command.restrictions.user_cooldown,
+            allowed_channels: command.restrictions.allowed_channels.clone(),
+            required_role: command.restrictions.required_role,
+        }
+    }
+
+    fn into_command(self) -> CommandInfo {
+        CommandInfo {
+            kind: self.kind,
+            content: self.content,
+            description: self.description,
+            title: self.title,
+            colour: self.colour,
+            image: self.image_url.filter(|_| self.kind == CommandKind::Embed),
+            restrictions: CommandRestrictions {
+                cooldown: self.cooldown.max(0),
+                user_cooldown: self.user_cooldown.max(0),
+                allowed_channels: self.allowed_channels,
+                required_role: self.required_role,
+            },
+            last_uses: LastUses::default(),
+        }
+    }
+}
+
+#[poise::command(
+    guild_only,
+    slash_command,
+    prefix_command,
+    subcommands(
+        "create", "modify", "add", "edit", "remove", "list", "export", "import"
+    ),
+    required_permissions = "MODERATE_MEMBERS",
+    default_member_permissions = "MODERATE_MEMBERS"
+)]
+pub async fn commands(_ctx: Context<'_>) -> Result<()> {
+    Ok(())
+}
+
+#[poise::command(guild_only, slash_command)]
+#[allow(clippy::too_many_arguments)]
+pub async fn add(
+    ctx: Context<'_>,
+    #[description = "The name of the command"] name: String,
+    #[description = "What to say, can use {user}, {user.mention}, {channel}, {count}, {random:1-6}, {arg}, {target}"]
+    content: Option<String>,
+    #[description = "Say the text, pick one of the | separated choices, show an embed or post an image"]
+    kind: Option<CommandKind>,
+    #[description = "What the command list shows about the command"]
+    #[max_length = 100]
+    description: Option<String>,
+    #[description = "The title of an embed command"] title: Option<String>,
+    #[description = "The 6 digit hex colour of an embed command"] colour: Option<String>,
+    #[description = "An image URL to show in an embed command"] image_url: Option<String>,
+    #[description = "The image an image command posts"] image: Option<Attachment>,
+    #[description = "Seconds before anyone can use the command again"] cooldown: Option<u32>,
+    #[description = "Seconds before the same person can use the command again"]
+    user_cooldown: Option<u32>,
+    #[description = "The only channels the command works in, as channel mentions"] channels: Option<
+        String,
+    >,
+    #[description = "The role people need to use the command"] role: Option<Role>,
+) -> Result<()> {
+    let name = name.to_lowercase();
+    let kind = kind.unwrap_or(CommandKind::Static);
+
+    if let Err(reason) = ensure_single_word(&name).and_then(|_| ensure_not_default_command(&name)) {
+        return bail_reply(ctx, reason.to_string()).await;
+    }
+
+    let guild = ctx
+        .guild_id()
+        .expect("Expected /commands add to be guild only.");
+
+    if command_exists(ctx, &guild, &name).await {
+        return bail_reply(ctx, "The command already exists.").await;
+    }
+
+    let Some(colour) = parse_colour(colour.as_deref()) else {
+        return bail_reply(ctx, "Please provide a valid hex color code.").await;
+    };
+    let restrictions =
+        match command_restrictions(cooldown, user_cooldown, channels.as_deref(), role) {
+            Ok(restrictions) => restrictions,
+            Err(reason) => return bail_reply(ctx, reason.to_string()).await,
+        };
+    let mut new_command = CommandInfo {
+        kind,
+        content: content.unwrap_or_default(),
+        description,
+        title,
+        colour,
+        image: image_url.filter(|_| kind == CommandKind::Embed),
+        restrictions,
+        last_uses: LastUses::default(),
+    };
+    if let Err(reason) = ensure_valid_kind(&new_command, image.as_ref()) {
+        return bail_reply(ctx, reason.to_string()).await;
+    }
+    if let Some(image) = &image {
+        match save_command_image(guild, &name, image).await {
+            Ok(path) => new_command.image = Some(path),
+            Err(reason) => return bail_reply(ctx, reason.to_string()).await,
+        }
+    }
+
+    let definition = build_command(&name, &new_command);
+    let saved_image = new_command.image_path().map(str::to_owned);
+    if let Err(e) = insert_command(ctx, &guild, &name, new_command).await {
+        if let Some(path) = &saved_image {
+            remove_command_image(path).await;
+        }
+        return Err(e);
+    }
+    if let Err(e) = register_command(ctx, &guild, definition).await {
+        // Nobody could use the command without the slash command, so don't keep any of it
+        delete_command(ctx, &guild, &name).await?;
+        if let Some(path) = &saved_image {
+            remove_command_image(path).await;
+        }
+        return Err(e);
+    }
+
+    ctx.send(ephemeral_reply("Command added")).await?;
+
+    Ok(())
+}
+
+#[poise::command(guild_only, slash_command)]
+#[allow(clippy::too_many_arguments)]
+pub async fn edit(
+    ctx: Context<'_>,
+    #[description = "The name of the command"] name: String,
+    #[description = "Say the text, pick one of the | separated choices, show an embed or post an image"]
+    kind: Option<CommandKind>,
+    #[description = "What to say, can use {user}, {user.mention}, {channel}, {count}, {random:1-6}, {arg}, {target}"]
+    content: Option<String>,
+    #[description = "What the command list shows about the command, or none"]
+    #[max_length = 100]
+    description: Option<String>,
+    #[description = "The title of an embed command, or none"] title: Option<String>,
+    #[description = "The 6 digit hex colour of an embed command, or none"] colour: Option<String>,
+    #[description = "An image URL to show in an embed command, or none"] image_url: Option<String>,
+    #[description = "A new image for an image command"] image: Option<Attachment>,
+    #[description = "Seconds before anyone can use the command again"] cooldown: Option<u32>,
+    #[description = "Seconds before the same person can use the command again"]
+    user_cooldown: Option<u32>,
+    #[description = "The only channels the command works in as channel mentions, or none"]
+    channels: Option<String>,
+    #[description = "The role people need to use the command"] role: Option<Role>,
+    #[description = "Let everyone use the command, without needing a role"] everyone: Option<bool>,
+) -> Result<()> {
+    let name = name.to_lowercase();
+    let guild = ctx
+        .guild_id()
+        .expect("Expected /commands edit to be guild only.");
+
+    let Some(mut updated_command) = stored_command(ctx, &guild, &name).await else {
+        return bail_reply(ctx, "The command does not exist.").await;
+    };
+    let stored_kind = updated_command.kind;
+    let stored_image = updated_command.image_path().map(str::to_owned);
+
+    if let Some(kind) = kind {
+        updated_command.kind = kind;
+    }
+    if let Some(content) = content {
+        updated_command.content = content;
+    }
+    if let Some(description) = description {
+        updated_command.description = none_to_clear(description);
+    }
+    if let Some(title) = title {
+        updated_command.title = none_to_clear(title);
+    }
+    if let Some(colour) = colour {
+        let Some(colour) = parse_colour(none_to_clear(colour).as_deref()) else {
+            return bail_reply(ctx, "Please provide a valid hex color code.").await;
+        };
+        updated_command.colour = colour;
+    }
+    updated_command.image = match updated_command.kind {
+        CommandKind::Embed => match image_url {
+            Some(image_url) => none_to_clear(image_url),
+            None => updated_command
+                .image
+                .filter(|_| stored_kind == CommandKind::Embed),
+        },
+        CommandKind::Image => stored_image.clone(),
+        CommandKind::Static | CommandKind::Choice => None,
+    };
+
+    let restrictions = &mut updated_command.restrictions;
+    if let Some(cooldown) = cooldown {
+        restrictions.cooldown = cooldown.into();
+    }
+    if let Some(user_cooldown) = user_cooldown {
+        restrictions.user_cooldown = user_cooldown.into();
+    }
+    if let Some(channels) = channels {
+        let channels = none_to_clear(channels)
+            .map(|c| CommandRestrictions::parse_channels(&c))
+            .transpose();
+        match channels {
+            Ok(channels) => restrictions.allowed_channels = channels.unwrap_or_default(),
+            Err(reason) => return bail_reply(ctx, reason.to_string()).await,
+        }
+    }
+    if let Some(role) = role {
+        restrictions.required_role = Some(role.id);
+    } else if everyone == Some(true) {
+        restrictions.required_role = None;
+    }
+
+    if let Err(reason) = ensure_valid_kind(&updated_command, image.as_ref()) {
+        return bail_reply(ctx, reason.to_string()).await;
+    }
+    if let Some(image) = &image {
+        if let Some(old_path) = &stored_image {
+            remove_command_image(old_path).await;
+        }
+        match save_command_image(guild, &name, image).await {
+            Ok(path) => updated_command.image = Some(path),
+            Err(reason) => return bail_reply(ctx, reason.to_string()).await,
+        }
+    } else if let Some(old_path) =
+        stored_image.filter(|_| updated_command.kind != CommandKind::Image)
+    {
+        remove_command_image(&old_path).await;
+    }
+
+    update_command(ctx, &guild, &name, updated_command).await?;
+    ctx.send(ephemeral_reply("The command has been updated."))
+        .await?;
+
+    Ok(())
+}
+
+// Parsing every option of /commands add and edit as prefix arguments is too much for the
+// compiler, so prefix commands only get the text. They come first to take the add and edit names.
+#[poise::command(guild_only, prefix_command, aliases("add"))]
+pub async fn create(
+    ctx: Context<'_>,
+    #[description = "The name of the command"] name: String,
+    #[description = "What the command should say"]
+    #[rest]
+    content: String,
+) -> Result<()> {
+    let name = name.to_lowercase();
+
+    if let Err(reason) = ensure_single_word(&name).and_then(|_| ensure_not_default_command(&name)) {
+        return bail_reply(ctx, reason.to_string()).await;
+    }
+
+    let guild = ctx
+        .guild_id()
+        .expect("Expected /commands create to be guild only.");
+
+    if command_exists(ctx, &guild, &name).await {
+        return bail_reply(ctx, "The command already exists.").await;
+    }
+
+    let new_command = CommandInfo {
+        kind: CommandKind::Static,
+        content,
+        description: None,
+        title: None,
+        colour: None,
+        image: None,
+        restrictions: CommandRestrictions::default(),
+        last_uses: LastUses::default(),
+    };
+    if let Err(reason) = ensure_valid_kind(&new_command, None) {
+        return bail_reply(ctx, reason.to_string()).await;
+    }
+
+    let definition = build_command(&name, &new_command);
+    insert_command(ctx, &guild, &name, new_command).await?;
+    register_command(ctx, &guild, definition).await?;
+
+    ctx.send(ephemeral_reply("Command added")).await?;
+
+    Ok(())
+}
+
+#[poise::command(guild_only, prefix_command, aliases("edit"))]
+pub async fn modify(
+    ctx: Context<'_>,
+    #[description = "The name of the command"] name: String,
+    #[description = "What the command should say"]
+    #[rest]
+    content: String,
+) -> Result<()> {
+    let name = name.to_lowercase();
+    let guild = ctx
+        .guild_id()
+        .expect("Expected /commands modify to be guild only.");
+
+    let Some(mut updated_command) = stored_command(ctx, &guild, &name).await else {
+        return bail_reply(ctx, "The command does not exist.").await;
+    };
+    updated_command.content = content;
+    if let Err(reason) = ensure_valid_kind(&updated_command, None) {
+        return bail_reply(ctx, reason.to_string()).await;
+    }
+
+    update_command(ctx, &guild, &name, updated_command).await?;
+    ctx.send(ephemeral_reply("The command has been updated."))
+        .await?;
+
+    Ok(())
+}
+
+#[poise::command(guild_only, slash_command, prefix_command, aliases("delete"))]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "The name of the command"] name: String,
+) -> Result<()> {
+    let name = name.to_lowercase();
+    let guild = ctx
+        .guild_id()
+        .expect("Expected /commands edit to be guild only.");
+
+    let stored_image = stored_image(ctx, &guild, &name).await;
+    delete_command(ctx, &guild, &name).await?;
+    unregister_command(ctx, &guild, &name).await?;
+    if let Some(path) = stored_image {
+        remove_command_image(&path).await;
+    }
+
+    ctx.send(ephemeral_reply("The command has been removed."))
+        .await?;
+
+    Ok(())
+}
+
+/// Show the commands of this server and how often they're used
+#[poise::command(guild_only, slash_command, prefix_command)]
+pub async fn list(ctx: Context<'_>) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /commands list to be guild only.")
+        .get() as i64;
+
+    let commands = sqlx::query_as!(
+        ListedCommand,
+        r#"SELECT name, kind AS "kind: CommandKind", description, uses, last_used
+        FROM SimpleCommands WHERE guild_id = ? ORDER BY name"#,
+        guild_id
+    )
+    .fetch_all(&ctx.data().database)
+    .await?;
+
+    if commands.is_empty() {
+        let msg = "This server doesn't have any commands yet, add one with /commands add.";
+        return bail_reply(ctx, msg).await;
+    }
+
+    let pages = commands
+        .chunks(COMMANDS_PER_PAGE)
+        .map(|page| {
+            page.iter()
+                .map(ListedCommand::line)
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        })
+        .collect::<Vec<_>>();
+
+    let mut page = 0;
+    let reply = CreateReply::default()
+        .embed(list_embed(&pages, page))
+        .components(page_buttons(page, pages.len()));
+    let handle = ctx.send(reply).await?;
+    if pages.len() == 1 {
+        return Ok(());
+    }
+
+    let message_id = handle.message().await?.id;
+    let author_id = ctx.author().id;
+    while let Some(interaction) = ComponentInteractionCollector::new(ctx)
+        .message_id(message_id)
+        .filter(move |f| f.user.id == author_id && f.data.custom_id.starts_with(PAGE_BUTTON))
+        .timeout(PAGE_TIMEOUT)
+        .await
+    {
+        page = match interaction.data.custom_id.rsplit(':').next() {
+            Some("previous") => page.saturating_sub(1),
+            _ => (page + 1).min(pages.len() - 1),
+        };
+
+        let message = CreateInteractionResponseMessage::new()
+            .embed(list_embed(&pages, page))
+            .components(page_buttons(page, pages.len()));
+        interaction
+            .create_response(ctx, update_response(message))
+            .await?;
+    }
+
+    let reply = CreateReply::default()
+        .embed(list_embed(&pages, page))
+        .components(Vec::new());
+    handle.edit(ctx, reply).await?;
+
+    Ok(())
+}
+
+fn list_embed(pages: &[String], page: usize) -> CreateEmbed {
+    CreateEmbed::new()
+        .title("Server commands")
+        .description(&pages[page])
+        .footer(CreateEmbedFooter::new(format!(
+            "Page {} of {}",
+            page + 1,
+            pages.len()
+        )))
+}
+
+fn page_buttons(page: usize, pages: usize) -> Vec<CreateActionRow> {
+    if pages <= 1 {
+        return Vec::new();
+    }
+
+    let previous = CreateButton::new(format!("{PAGE_BUTTON}:previous"))
+        .emoji('◀')
+        .style(ButtonStyle::Secondary)
+        .disabled(page == 0);
+    let next = CreateButton::new(format!("{PAGE_BUTTON}:next"))
+        .emoji('▶')
+        .style(ButtonStyle::Secondary)
+        .disabled(page + 1 == pages);
+
+    vec![CreateActionRow::Buttons(vec![previous, next])]
+}
+
+/// Download every command of this server as a JSON file
+#[poise::command(guild_only, slash_command)]
+pub async fn export(ctx: Context<'_>) -> Result<()> {
+    let guild = ctx
+        .guild_id()
+        .expect("Expected /commands export to be guild only.");
+
+    let (mut exported, skipped) = {
+        let map = ctx.data().simple_commands.read().await;
+        let guild_commands = map.get(&(guild.get() as i64));
+        let commands = guild_commands.iter().flat_map(|commands| commands.iter());
+
+        let (images, others): (Vec<_>, Vec<_>) =
+            commands.partition(|(_, command)| command.kind == CommandKind::Image);
+        let exported = others
+            .into_iter()
+            .map(|(name, command)| ExportedCommand::new(name, command))
+            .collect::<Vec<_>>();
+
+        (exported, images.len())
+    };
+    exported.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let json = serde_json::to_vec_pretty(&exported)?;
+    let mut content = format!("Exported {} commands.", exported.len());
+    if skipped > 0 {
+        content.push_str(&format!(
+            " {skipped} image commands were left out, their images can't be exported."
+        ));
+    }
+
+    let reply = ephemeral_reply(content).attachment(CreateAttachment::bytes(
+        json,
+        format!("commands-{guild}.json"),
+    ));
+    ctx.send(reply).await?;
+
+    Ok(())
+}
+
+/// Add the commands from a file made with /commands export
+#[poise::command(guild_only, slash_command)]
+pub async fn import(
+    ctx: Context<'_>,
+    #[description = "The JSON file made with /commands export"] file: Attachment,
+) -> Result<()> {
+    let guild = ctx
+        .guild_id()
+        .expect("Expected /commands import to be guild only.");
+
+    let bytes = file.download().await?;
+    let exported = match serde_json::from_slice::<Vec<ExportedCommand>>(&bytes) {
+        Ok(exported) => exported,
+        Err(e) => return bail_reply(ctx, format!("That isn't a valid commands file: {e}")).await,
+    };
+
+    let mut skipped = Vec::new();
+    let mut imported: Vec<(String, CommandInfo)> = Vec::new();
+    for command in exported {
+        let name = command.name.to_lowercase();
+        let command = command.into_command();
+
+        let problem = if imported.iter().any(|(n, _)| *n == name) {
+            Err(anyhow!("It's in the file more than once."))
+        } else if command_exists(ctx, &guild, &name).await {
+            Err(anyhow!("A command with that name already exists."))
+        } else {
+            ensure_importable(&name, &command)
+        };
+
+        match problem {
+            Ok(()) => imported.push((name, command)),
+            Err(reason) => skipped.push(format!("`{name}`: {reason}")),
+        }
+    }
+
+    if !imported.is_empty() {
+        insert_commands(ctx, &guild, imported.iter().map(|(n, c)| (n.as_str(), c))).await?;
+
+        let count = imported.len();
+        let definitions = {
+            let mut map = ctx.data().simple_commands.write().await;
+            let guild_commands = map.entry(guild.get() as i64).or_default();
+            guild_commands.extend(imported);
+            guild_commands
+                .iter()
+                .map(|(name, command)| build_command(name, command))
+                .collect::<Vec<_>>()
+        };
+        guild.set_commands(ctx, definitions).await?;
+
+        skipped.insert(0, format!("Imported {count} commands."));
+    } else {
+        skipped.insert(0, "No commands were imported.".to_owned());
+    }
+
+    if skipped.len() > MAX_REPORTED_LINES {
+        let hidden = skipped.len() - MAX_REPORTED_LINES;
+        skipped.truncate(MAX_REPORTED_LINES);
+        skipped.push(format!("...and {hidden} more were skipped."));
+    }
+    let report = skipped.join("\n");
+    ctx.send(ephemeral_reply(report)).await?;
+
+    Ok(())
+}
+
+fn ensure_importable(name: &str, command: &CommandInfo) -> Result<()> {
+    if name.is_empty() || name.len() > 32 {
+        bail!("Command names have to be 1 to 32 characters long.");
+    }
+    ensure_single_word(name)?;
+    ensure_not_default_command(name)?;
+    if command.kind == CommandKind::Image {
+        bail!("Image commands can't be imported.");
+    }
+    if command
+        .description
+        .as_ref()
+        .is_some_and(|d| d.chars().count() > 100)
+    {
+        bail!("The description can be at most 100 characters long.");
+    }
+    if command.colour.is_some_and(|c| !(0..=0xFFFFFF).contains(&c)) {
+        bail!("The colour isn't a valid hex colour.");
+    }
+
+    ensure_valid_kind(command, None)
+}
+
+async fn command_exists(ctx: Context<'_>, guild_id: &GuildId, name: &str) -> bool {
+    let map = ctx.data().simple_commands.read().await;
+    map.get(&(guild_id.get() as i64))
+        .is_some_and(|commands| commands.contains_key(name))
+}
+
+/// A copy of a guild command to make changes to, without its last uses.
+async fn stored_command(ctx: Context<'_>, guild_id: &GuildId, name: &str) -> Option<CommandInfo> {
+    let map = ctx.data().simple_commands.read().await;
+    let command = map.get(&(guild_id.get() as i64))?.get(name)?;
+
+    Some(CommandInfo {
+        kind: command.kind,
+        content: command.content.clone(),
+        description: command.description.clone(),
+        title: command.title.clone(),
+        colour: command.colour,
+        image: command.image.clone(),
+        restrictions: command.restrictions.clone(),
+        last_uses: LastUses::default(),
+    })
+}
+
+async fn stored_image(ctx: Context<'_>, guild_id: &GuildId, name: &str) -> Option<String> {
+    let map = ctx.data().simple_commands.read().await;
+    let command = map.get(&(guild_id.get() as i64))?.get(name)?;
+
+    command.image_path().map(str::to_owned)
+}
+
+fn command_restrictions(
+    cooldown: Option<u32>,
+    user_cooldown: Option<u32>,
+    channels: Option<&str>,
+    role: Option<Role>,
+) -> Result<CommandRestrictions> {
+    let allowed_channels = channels
+        .map(CommandRestrictions::parse_channels)
+        .transpose()?
+        .unwrap_or_default();
+
+    Ok(CommandRestrictions {
+        cooldown: cooldown.unwrap_or_default().into(),
+        user_cooldown: user_cooldown.unwrap_or_default().into(),
+        allowed_channels,
+        required_role: role.map(|r| r.id),
+    })
+}
+
+/// `None` when the colour isn't a valid hex code, `Some(None)` when no colour was given.
+fn parse_colour(colour: Option<&str>) -> Option<Option<i64>> {
+    match colour {
+        Some(hexcode) => to_color(hexcode).map(|c| Some(c.into())),
+        None => Some(None),
+    }
+}
+
+fn ensure_valid_kind(command: &CommandInfo, upload: Option<&Attachment>) -> Result<()> {
+    match command.kind {
+        CommandKind::Static | CommandKind::Choice if command.content.is_empty() => {
+            bail!("A text command needs some content.")
+        }
+        CommandKind::Embed if command.content.is_empty() && command.title.is_none() => {
+            bail!("An embed command needs a title or some content.")
+        }
+        CommandKind::Image if upload.is_none() && command.image.is_none() => {
+            bail!("An image command needs an uploaded image.")
+        }
+        CommandKind::Static | CommandKind::Choice | CommandKind::Embed if upload.is_some() => {
+            bail!("Only image commands can have an uploaded image, embeds use an image URL.")
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn insert_command(
+    ctx: Context<'_>,
+    guild_id: &GuildId,
+    name: &str,
+    new_command: CommandInfo,
+) -> Result<()> {
+    let data = ctx.data();
+    let mut map = data.simple_commands.write().await;
+
+    let guild_id = guild_id.get() as i64;
+    let guild_commands = map.entry(guild_id).or_default();
+    let Entry::Vacant(entry) = guild_commands.entry(name.to_owned()) else {
+        // TODO: Should this be outside?
+        return bail_reply(ctx, "The command already exists.").await;
+    };
+
+    insert_command_row(&data.database, guild_id, name, &new_command).await?;
+    entry.insert(new_command);
+
+    Ok(())
+}
+/// Save a batch of new commands, either all of them or none.
+async fn insert_commands<'a>(
+    ctx: Context<'_>,
+    guild_id: &GuildId,
+    commands: impl Iterator<Item = (&'a str, &'a CommandInfo)>,
+) -> Result<()> {
+    let guild_id = guild_id.get() as i64;
+    let mut transaction = ctx.data().database.begin().await?;
+    for (name, command) in commands {
+        insert_command_row(&mut transaction, guild_id, name, command).await?;
+    }
+    transaction.commit().await?;
+
+    Ok(())
+}
+async fn insert_command_row(
+    executor: impl SqliteExecutor<'_>,
+    guild_id: i64,
+    name: &str,
+    new_command: &CommandInfo,
+) -> Result<()> {
+    let content = &new_command.content;
+    let description = &new_command.description;
+    let kind = new_command.kind;
+    let restrictions = &new_command.restrictions;
+    let allowed_channels = restrictions.channels_to_db();
+    let required_role = restrictions.required_role.map(|r| r.get() as i64);
+    sqlx::query!(
+        r#"INSERT INTO SimpleCommands (guild_id, name, kind, content, description, title,
+            colour, image, cooldown, user_cooldown, allowed_channels, required_role)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+        guild_id,
+        name,
+        kind,
+        content,
+        description,
+        new_command.title,
+        new_command.colour,
+        new_command.image,
+        restrictions.cooldown,
+        restrictions.user_cooldown,
+        allowed_channels,
+        required_role
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+async fn update_command(
+    ctx: Context<'_>,
+    guild_id: &GuildId,
+    name: &str,
+    command: CommandInfo,
+) -> Result<()> {
+    let data = ctx.data();
+    let mut map = data.simple_commands.write().await;
+
+    let guild_id = guild_id.get() as i64;
+    let Some(guild_commands) = map.get_mut(&guild_id) else {
+        return bail_reply(ctx, "This guild does not have this command.").await;
+    };
+    let Some(entry) = guild_commands.get_mut(name) else {
+        return bail_reply(ctx, "The command does not exist.").await;
+    };
+
+    let content = &command.content;
+    let kind = &command.kind;
+    let restrictions = &command.restrictions;
+    let allowed_channels = restrictions.channels_to_db();
+    let required_role = restrictions.required_role.map(|r| r.get() as i64);
+    sqlx::query!(
+        r#"UPDATE OR IGNORE SimpleCommands SET kind = ?, content = ?, description = ?, title = ?,
+            colour = ?, image = ?, cooldown = ?, user_cooldown = ?, allowed_channels = ?,
+            required_role = ?
+        WHERE guild_id = ? AND name = ?"#,
+        kind,
+        content,
+        command.description,
+        command.title,
+        command.colour,
+        command.image,
+        restrictions.cooldown,
+        restrictions.user_cooldown,
+        allowed_channels,
+        required_role,
+        guild_id,
+        name
+    )
+    .execute(&data.database)
+    .await?;
+
+    // The content decides which options the command has
+    let definition = build_command(name, &command);
+    register_command(ctx, &GuildId::new(guild_id as u64), definition).await?;
+
+    // Editing a command shouldn't be a way around its cooldown
+    let mut command = command;
+    command.last_uses = std::mem::take(&mut entry.last_uses);
+    *entry = command;
+
+    Ok(())
+}
+async fn delete_command(ctx: Context<'_>, guild_id: &GuildId, name: &str) -> Result<()> {
+    let data = ctx.data();
+    let mut map = data.simple_commands.write().await;
+
+    let guild_id = guild_id.get() as i64;
+    let Some(guild_commands) = map.get_mut(&guild_id) else {
+        return bail_reply(ctx, "This guild does not have this command.").await;
+    };
+    let Entry::Occupied(entry) = guild_commands.entry(name.to_owned()) else {
+        return bail_reply(ctx, "This command name does not exist.").await;
+    };
+
+    sqlx::query!(
+        "DELETE FROM SimpleCommands WHERE guild_id = ? AND name = ?",
+        guild_id,
+        name
+    )
+    .execute(&data.database)
+    .await?;
+
+    entry.remove_entry();
+
+    Ok(())
+}
+
+async fn register_command(
+    ctx: Context<'_>,
+    guild_id: &GuildId,
+    definition: CreateCommand,
+) -> Result<()> {
+    guild_id.create_command(ctx, definition).await?;
+
+    Ok(())
+}
+/// The slash command definition for a guild command, with an option for every
+/// template variable that needs one.
+pub fn build_command(name: &str, command: &CommandInfo) -> CreateCommand {
+    let description = command
+        .description
+        .as_deref()
+        .unwrap_or("A simple text command");
+    let mut definition = CreateCommand::new(name).description(description);
+
+    if command.uses_variable(ARG_OPTION) {
+        definition = definition.add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            ARG_OPTION,
+            "Text to put in the response",
+        ));
+    }
+    if command.uses_variable(TARGET_OPTION) {
+        definition = definition.add_option(CreateCommandOption::new(
+            CommandOptionType::User,
+            TARGET_OPTION,
+            "Who the command is aimed at",
+        ));
+    }
+
+    definition
+}
+async fn unregister_command(ctx: Context<'_>, guild_id: &GuildId, name: &str) -> Result<()> {
+    let Some(command_to_delete) = guild_id
+        .get_commands(ctx)
+        .await?
+        .into_iter()
+        .find(|c| c.name == name)
+    else {
+        eprintln!("Command {name} was present in the hashmap but in the guild commands");
+        return Ok(());
+    };
+
+    guild_id.delete_command(ctx, command_to_delete.id).await?;
+
+    Ok(())
+}
+
+fn ensure_not_default_command(name: &str) -> Result<()> {
+    if DEFAULT_COMMANDS
+        .get()
+        .expect("Expected default commands to be initialized.")
+        .iter()
+        .any(|n| n == name)
+    {
+        bail!("Cannot add command with that name because it's already taken by a default command.");
+    }
+
+    Ok(())
+}
+fn ensure_single_word(name: &str) -> Result<()> {
+    if !name.chars().all(|c| c.is_ascii_alphanumeric()) {
+        bail!("Command name must be a single word.");
+    };
+
+    Ok(())
+}
+
+pub async fn try_intercepting_command_call(
+    ctx: &SerenityContext,
+    user_data: &Data,
+    interaction: &Interaction,
+) -> Result<()> {
+    let interaction = interaction.clone();
+
+    let Some(command) = interaction.command() else {
+        return Ok(());
+    };
+
+    let map = user_data.simple_commands.read().await;
+    let Some(guild_id) = command.guild_id else {
+        return Ok(());
+    };
+    let Some(guild_commands) = map.get(&(guild_id.get() as i64)) else {
+        return Ok(());
+    };
+
+    if let Some(dynamic_command) = guild_commands.get(&command.data.name) {
+        let restrictions = &dynamic_command.restrictions;
+        if let Err(reason) = restrictions
+            .ensure_allowed(&command)
+            .and_then(|_| restrictions.update_cooldown(&dynamic_command.last_uses, command.user.id))
+        {
+            let resp = response(ephemeral_text_message(reason.to_string()));
+            command.create_response(ctx, resp).await?;
+            return Ok(());
+        }
+
+        let count = count_use(user_data, guild_id, &command.data.name).await?;
+        let message = respond_to_command_call(dynamic_command, &command, count).await?;
+        command.create_response(ctx, response(message)).await?;
+    };
+
+    Ok(())
+}
+
+/// Bump how many times the command was used and return the new count.
+async fn count_use(user_data: &Data, guild_id: GuildId, name: &str) -> Result<i64> {
+    let guild_id = guild_id.get() as i64;
+    let now = Utc::now().naive_utc();
+    let row = sqlx::query!(
+        r#"UPDATE SimpleCommands SET uses = uses + 1, last_used = ?
+        WHERE guild_id = ? AND name = ?
+        RETURNING uses AS "uses!""#,
+        now,
+        guild_id,
+        name
+    )
+    .fetch_one(&user_data.database)
+    .await?;
+
+    Ok(row.uses)
+}
+
+async fn respond_to_command_call(
+    dynamic_command: &CommandInfo,
+    command: &CommandInteraction,
+    count: i64,
+) -> Result<CreateInteractionResponseMessage> {
+    let (text, title) = render_command(dynamic_command, command, count);
+    // Commands can be filled in by anyone, only the people it's about get pinged
+    let mentions = CreateAllowedMentions::new()
+        .users(std::iter::once(command.user.id).chain(command_target(command)));
+
+    let message = match dynamic_command.kind {
+        CommandKind::Static | CommandKind::Choice => text_message(text),
+        CommandKind::Embed => {
+            let mut embed = CreateEmbed::new().description(text);
+            if let Some(title) = title {
+                embed = embed.title(title);
+            }
+            if let Some(colour) = dynamic_command.colour {
+                embed = embed.colour(colour as u32);
+            }
+            if let Some(url) = &dynamic_command.image {
+                embed = embed.image(url);
+            }
+            embed_message(embed)
+        }
+        CommandKind::Image => {
+            let mut message = CreateInteractionResponseMessage::new();
+            if !text.is_empty() {
+                message = message.content(text);
+            }
+            if let Some(path) = dynamic_command.image_path() {
+                message = message.add_file(CreateAttachment::path(path).await?);
+            }
+            message
+        }
+    };
+
+    Ok(message.allowed_mentions(mentions))
+}
+
+fn option<'a>(command: &'a CommandInteraction, name: &str) -> Option<&'a CommandDataOptionValue> {
+    command
+        .data
+        .options
+        .iter()
+        .find(|o| o.name == name)
+        .map(|o| &o.value)
+}
+
+fn command_target(command: &CommandInteraction) -> Option<UserId> {
+    option(command, TARGET_OPTION).and_then(|v| v.as_user_id())
+}
+
+/// Fill in the templates of the command's content and title.
+fn render_command(
+    dynamic_command: &CommandInfo,
+    command: &CommandInteraction,
+    count: i64,
+) -> (String, Option<String>) {
+    let mut rng = rand::thread_rng();
+    let content = match dynamic_command.kind {
+        CommandKind::Choice => dynamic_command.content.split('|').choose(&mut rng).unwrap(),
+        _ => dynamic_command.content.as_str(),
+    };
+
+    let context = TemplateContext {
+        user_name: command.member.as_ref().map_or_else(
+            || {
+                command
+                    .user
+                    .global_name
+                    .as_deref()
+                    .unwrap_or(&command.user.name)
+            },
+            |m| m.display_name(),
+        ),
+        user_id: command.user.id,
+        channel_id: command.channel_id,
+        count,
+        arg: option(command, ARG_OPTION).and_then(|v| v.as_str()),
+        target: command_target(command),
+    };
+
+    let title = dynamic_command
+        .title
+        .as_deref()
+        .map(|title| render(title, &context, &mut rng));
+
+    (render(content, &context, &mut rng), title)
+}
@@ -0,0 +1,32 @@
+use std::path::Path;
+
+use anyhow::bail;
+use poise::serenity_prelude::{Attachment, GuildId};
+
+use crate::commands::uploads::{remove_upload, save_upload};
+use crate::Result;
+
+const COMMAND_IMAGES_PATH: &str = "./assets/commands";
+
+/// Download the image uploaded for a command into the guild's asset folder and return
+/// the path it's stored at.
+pub async fn save_command_image(
+    guild_id: GuildId,
+    name: &str,
+    image: &Attachment,
+) -> Result<String> {
+    if !image
+        .content_type
+        .as_deref()
+        .is_some_and(|t| t.starts_with("image/"))
+    {
+        bail!("The uploaded file has to be an image.");
+    }
+
+    let folder = Path::new(COMMAND_IMAGES_PATH).join(guild_id.to_string());
+    save_upload(&folder, name, image).await
+}
+
+pub async fn remove_command_image(path: &str) {
+    remove_upload(path).await;
+}
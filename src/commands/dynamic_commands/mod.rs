@@ -0,0 +1,7 @@
+mod commands;
+mod images;
+mod restrictions;
+mod template;
+
+pub use commands::*;
+pub use restrictions::*;
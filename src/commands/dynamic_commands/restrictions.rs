@@ -0,0 +1,180 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Mutex,
+    },
+};
+
+use anyhow::bail;
+use chrono::Utc;
+use poise::serenity_prelude::{ChannelId, CommandInteraction, Mentionable, RoleId, UserId};
+
+use crate::{config::parse_channels, Result};
+
+/// Who can use a guild command, where and how often.
+#[derive(Default, Clone)]
+pub struct CommandRestrictions {
+    /// Seconds before anyone can use the command again
+    pub cooldown: i64,
+    /// Seconds before the same person can use the command again
+    pub user_cooldown: i64,
+    /// The command works anywhere when this is empty
+    pub allowed_channels: Vec<ChannelId>,
+    pub required_role: Option<RoleId>,
+}
+
+/// Timestamps of the last time a command was used, to enforce its cooldowns.
+#[derive(Default)]
+pub struct LastUses {
+    everyone: AtomicI64,
+    users: Mutex<HashMap<UserId, i64>>,
+}
+
+impl CommandRestrictions {
+    pub fn parse_channels(channels: &str) -> Result<Vec<ChannelId>> {
+        parse_channels(channels)
+    }
+
+    pub fn channels_to_db(&self) -> Option<String> {
+        if self.allowed_channels.is_empty() {
+            return None;
+        }
+
+        let channels = self
+            .allowed_channels
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Some(channels)
+    }
+
+    /// Reject the call if it's in the wrong channel or the caller doesn't have the role.
+    pub fn ensure_allowed(&self, command: &CommandInteraction) -> Result<()> {
+        if !self.allowed_channels.is_empty() && !self.allowed_channels.contains(&command.channel_id)
+        {
+            let channels = self
+                .allowed_channels
+                .iter()
+                .map(|c| c.mention().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            bail!("This command can only be used in {channels}.");
+        }
+
+        if let Some(role) = self.required_role {
+            let has_role = command
+                .member
+                .as_ref()
+                .is_some_and(|m| m.roles.contains(&role));
+            if !has_role {
+                bail!("You need the {} role to use this command.", role.mention());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Start the cooldowns of the command, or say when it can be used again.
+    pub fn update_cooldown(&self, last_uses: &LastUses, user_id: UserId) -> Result<()> {
+        self.update_cooldown_at(last_uses, user_id, Utc::now().timestamp())
+    }
+
+    fn update_cooldown_at(&self, last_uses: &LastUses, user_id: UserId, now: i64) -> Result<()> {
+        let last_called = last_uses.everyone.load(Ordering::Relaxed);
+        let cooldown_end = last_called + self.cooldown;
+        if self.cooldown > 0 && cooldown_end > now {
+            bail!("The command will be off cooldown <t:{cooldown_end}:R>");
+        }
+
+        let mut users = last_uses.users.lock().unwrap();
+        // Forget everyone whose cooldown ran out, otherwise every caller is kept forever
+        users.retain(|_, &mut last_use| last_use + self.user_cooldown > now);
+        if self.user_cooldown > 0 {
+            if let Some(cooldown_end) = users.get(&user_id).map(|t| t + self.user_cooldown) {
+                if cooldown_end > now {
+                    bail!("You can use the command again <t:{cooldown_end}:R>");
+                }
+            }
+            users.insert(user_id, now);
+        }
+
+        last_uses.everyone.store(now, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn restrictions(cooldown: i64, user_cooldown: i64) -> CommandRestrictions {
+        CommandRestrictions {
+            cooldown,
+            user_cooldown,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn global_cooldown_blocks_everyone() {
+        let restrictions = restrictions(60, 0);
+        let last_uses = LastUses::default();
+
+        assert!(restrictions
+            .update_cooldown_at(&last_uses, UserId::new(1), 1000)
+            .is_ok());
+        assert!(restrictions
+            .update_cooldown_at(&last_uses, UserId::new(2), 1030)
+            .is_err());
+        assert!(restrictions
+            .update_cooldown_at(&last_uses, UserId::new(2), 1060)
+            .is_ok());
+    }
+
+    #[test]
+    fn user_cooldown_only_blocks_the_same_user() {
+        let restrictions = restrictions(0, 60);
+        let last_uses = LastUses::default();
+
+        assert!(restrictions
+            .update_cooldown_at(&last_uses, UserId::new(1), 1000)
+            .is_ok());
+        assert!(restrictions
+            .update_cooldown_at(&last_uses, UserId::new(2), 1001)
+            .is_ok());
+        assert!(restrictions
+            .update_cooldown_at(&last_uses, UserId::new(1), 1002)
+            .is_err());
+    }
+
+    #[test]
+    fn parses_channel_lists() {
+        let channels = CommandRestrictions::parse_channels("<#1>, 2 <#3>").unwrap();
+        assert_eq!(
+            channels,
+            vec![ChannelId::new(1), ChannelId::new(2), ChannelId::new(3)]
+        );
+
+        assert!(CommandRestrictions::parse_channels("general").is_err());
+    }
+
+    #[test]
+    fn expired_user_cooldowns_are_forgotten() {
+        let restrictions = restrictions(0, 60);
+        let last_uses = LastUses::default();
+
+        for user in 1..=3 {
+            restrictions
+                .update_cooldown_at(&last_uses, UserId::new(user), 1000)
+                .unwrap();
+        }
+        restrictions
+            .update_cooldown_at(&last_uses, UserId::new(4), 1060)
+            .unwrap();
+
+        let users = last_uses.users.lock().unwrap();
+        assert_eq!(users.keys().collect::<Vec<_>>(), vec![&UserId::new(4)]);
+    }
+}
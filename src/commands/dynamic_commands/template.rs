@@ -0,0 +1,142 @@
+use std::sync::OnceLock;
+
+use poise::serenity_prelude::{ChannelId, Mentionable, UserId};
+use rand::Rng;
+use regex::{Captures, Regex};
+
+pub const ARG_OPTION: &str = "arg";
+pub const TARGET_OPTION: &str = "target";
+
+static VARIABLE: OnceLock<Regex> = OnceLock::new();
+
+/// Everything a command template can refer to when it's called.
+pub struct TemplateContext<'a> {
+    pub user_name: &'a str,
+    pub user_id: UserId,
+    pub channel_id: ChannelId,
+    pub count: i64,
+    pub arg: Option<&'a str>,
+    pub target: Option<UserId>,
+}
+
+/// Fill in the `{variables}` of a command's content. Anything that isn't a known variable
+/// is left untouched so braces can still be used in plain text.
+pub fn render(template: &str, context: &TemplateContext, rng: &mut impl Rng) -> String {
+    let variable = VARIABLE.get_or_init(|| {
+        Regex::new(r"\{([a-z.]+)(?::([^{}]*))?\}").expect("Template variable regex to be valid")
+    });
+
+    variable
+        .replace_all(template, |cap: &Captures| {
+            let value = match (&cap[1], cap.get(2).map(|m| m.as_str())) {
+                ("user", None) => Some(context.user_name.to_string()),
+                ("user.mention", None) => Some(context.user_id.mention().to_string()),
+                ("channel", None) => Some(context.channel_id.mention().to_string()),
+                ("count", None) => Some(context.count.to_string()),
+                ("arg", None) => Some(context.arg.unwrap_or_default().to_string()),
+                ("target", None) => Some(
+                    context
+                        .target
+                        .unwrap_or(context.user_id)
+                        .mention()
+                        .to_string(),
+                ),
+                ("random", Some(range)) => random_in_range(range, rng).map(|n| n.to_string()),
+                _ => None,
+            };
+
+            value.unwrap_or_else(|| cap[0].to_string())
+        })
+        .into_owned()
+}
+
+/// Whether the command needs to be registered with an option for this variable.
+pub fn uses_variable(template: &str, name: &str) -> bool {
+    template.contains(&format!("{{{name}}}"))
+}
+
+fn random_in_range(range: &str, rng: &mut impl Rng) -> Option<i64> {
+    // Skip the first character so the low end can be negative
+    let range = range.trim();
+    let split = range.get(1..)?.find('-')? + 1;
+    let (low, high) = (&range[..split], &range[split + 1..]);
+    let low = low.trim().parse::<i64>().ok()?;
+    let high = high.trim().parse::<i64>().ok()?;
+
+    (low <= high).then(|| rng.gen_range(low..=high))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn context(arg: Option<&str>, target: Option<UserId>) -> TemplateContext<'_> {
+        TemplateContext {
+            user_name: "twiggy",
+            user_id: UserId::new(1),
+            channel_id: ChannelId::new(2),
+            count: 41,
+            arg,
+            target,
+        }
+    }
+
+    #[test]
+    fn fills_in_variables() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let text = render(
+            "{user} ({user.mention}) hugged {target} in {channel}, {count} hugs so far",
+            &context(None, Some(UserId::new(3))),
+            &mut rng,
+        );
+
+        assert_eq!(text, "twiggy (<@1>) hugged <@3> in <#2>, 41 hugs so far");
+    }
+
+    #[test]
+    fn missing_options_have_fallbacks() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let text = render("[{arg}] {target}", &context(None, None), &mut rng);
+
+        assert_eq!(text, "[] <@1>");
+    }
+
+    #[test]
+    fn random_stays_in_range() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let text = render("{random:1-6}", &context(None, None), &mut rng);
+            let roll = text.parse::<i64>().unwrap();
+            assert!((1..=6).contains(&roll));
+        }
+    }
+
+    #[test]
+    fn random_ranges_can_be_negative() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let roll = random_in_range("-5-5", &mut rng).unwrap();
+            assert!((-5..=5).contains(&roll));
+        }
+        assert_eq!(random_in_range("-3--3", &mut rng), Some(-3));
+    }
+
+    #[test]
+    fn unknown_variables_are_left_alone() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let text = render(
+            "{nope} {random:6-1} {random:a-b} {user",
+            &context(Some("x"), None),
+            &mut rng,
+        );
+
+        assert_eq!(text, "{nope} {random:6-1} {random:a-b} {user");
+    }
+
+    #[test]
+    fn detects_option_variables() {
+        assert!(uses_variable("hi {arg}!", ARG_OPTION));
+        assert!(!uses_variable("hi {args}!", ARG_OPTION));
+    }
+}
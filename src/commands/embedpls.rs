@@ -1,6 +1,6 @@
 use serenity::all::Member;
 
-use crate::{common::bail_reply, config::EMBED_ROLE, Context, Result};
+use crate::{common::bail_reply, Context, Result};
 
 /// Toggle the ability of embedding images/videos
 #[poise::command(guild_only, slash_command, prefix_command)]
@@ -18,11 +18,12 @@ pub async fn embedpls(
             .into_owned(),
     };
 
-    if member.roles.contains(&EMBED_ROLE) {
-        member.remove_role(ctx, EMBED_ROLE).await?;
+    let embed_role = ctx.data().guild_config(member.guild_id).await.embed_role;
+    if member.roles.contains(&embed_role) {
+        member.remove_role(ctx, embed_role).await?;
         bail_reply(ctx, "The embed role has been removed.").await
     } else {
-        member.add_role(ctx, EMBED_ROLE).await?;
+        member.add_role(ctx, embed_role).await?;
         bail_reply(ctx, "The embed role has been added.").await
     }
 }
@@ -0,0 +1,141 @@
+use poise::serenity_prelude::{CreateEmbed, Mentionable, User};
+use poise::CreateReply;
+
+use crate::{
+    common::{bail_reply, ephemeral_reply},
+    config::DEFAULT_GREETING_TIMEOUT,
+    events::{fetch_greetings, reload_greetings, Greeting},
+    Context, Result,
+};
+
+#[poise::command(
+    guild_only,
+    slash_command,
+    subcommands("set", "remove", "list"),
+    required_permissions = "MODERATE_MEMBERS",
+    default_member_permissions = "MODERATE_MEMBERS"
+)]
+pub async fn greeter(_ctx: Context<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// MOD ONLY: Greet someone when they talk
+#[poise::command(guild_only, slash_command)]
+async fn set(
+    ctx: Context<'_>,
+    #[description = "Who to greet"] user: User,
+    #[description = "| separated greetings picked at random, can use {user} and {user.mention}"]
+    greetings: String,
+    #[description = "Seconds before greeting them again, 10 hours by default"] timeout: Option<u32>,
+    #[description = "Greet their first message of every day instead"] daily: Option<bool>,
+) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /greeter set to be guild only.");
+
+    let greeting = Greeting {
+        user_id: user.id,
+        templates: greetings,
+        timeout: timeout.map_or(DEFAULT_GREETING_TIMEOUT.num_seconds(), i64::from),
+        daily: daily.unwrap_or(false),
+    };
+    if greeting.templates().next().is_none() {
+        return bail_reply(ctx, "Please provide at least one greeting.").await;
+    }
+
+    let db_guild_id = guild_id.get() as i64;
+    let user_id = user.id.get() as i64;
+    sqlx::query!(
+        "INSERT INTO Greeting (guild_id, user_id, templates, timeout, daily)
+        VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT (guild_id, user_id) DO UPDATE
+        SET templates = excluded.templates, timeout = excluded.timeout, daily = excluded.daily",
+        db_guild_id,
+        user_id,
+        greeting.templates,
+        greeting.timeout,
+        greeting.daily
+    )
+    .execute(&ctx.data().database)
+    .await?;
+
+    reload_greetings(&ctx.data().database, guild_id).await?;
+    ctx.send(ephemeral_reply(format!(
+        "I will greet {} from now on.",
+        user.mention()
+    )))
+    .await?;
+
+    Ok(())
+}
+
+/// MOD ONLY: Stop greeting someone
+#[poise::command(guild_only, slash_command)]
+async fn remove(
+    ctx: Context<'_>,
+    #[description = "Who to stop greeting"] user: User,
+) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /greeter remove to be guild only.");
+
+    let db_guild_id = guild_id.get() as i64;
+    let user_id = user.id.get() as i64;
+    let removed = sqlx::query!(
+        "DELETE FROM Greeting WHERE guild_id = ? AND user_id = ?",
+        db_guild_id,
+        user_id
+    )
+    .execute(&ctx.data().database)
+    .await?
+    .rows_affected();
+
+    if removed == 0 {
+        return bail_reply(ctx, "I wasn't greeting them.").await;
+    }
+
+    reload_greetings(&ctx.data().database, guild_id).await?;
+    ctx.send(ephemeral_reply(format!(
+        "I won't greet {} anymore.",
+        user.mention()
+    )))
+    .await?;
+
+    Ok(())
+}
+
+/// MOD ONLY: Show everyone that gets greeted
+#[poise::command(guild_only, slash_command)]
+async fn list(ctx: Context<'_>) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /greeter list to be guild only.");
+
+    let greetings = fetch_greetings(&ctx.data().database, guild_id).await?;
+    if greetings.is_empty() {
+        return bail_reply(ctx, "I'm not greeting anyone yet.").await;
+    }
+
+    let description = greetings
+        .iter()
+        .map(|g| {
+            let when = if g.daily {
+                "once a day".to_string()
+            } else {
+                format!("every {}s", g.timeout)
+            };
+            format!("{} ({when}): {}", g.user_id.mention(), g.templates)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let embed = CreateEmbed::default()
+        .colour(0x5865F2)
+        .title("Greetings")
+        .description(description);
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use poise::{ChoiceParameter, CreateReply};
+use serenity::all::CreateEmbed;
+
+use crate::{
+    common::{bail_reply, ephemeral_reply},
+    config::{save_guild_config_value, ConfigKey},
+    Context, Result,
+};
+
+#[poise::command(
+    guild_only,
+    slash_command,
+    subcommands("get", "set", "list"),
+    required_permissions = "ADMINISTRATOR",
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn config(_ctx: Context<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// ADMIN ONLY: Show the current value of a setting
+#[poise::command(guild_only, slash_command)]
+async fn get(
+    ctx: Context<'_>,
+    #[description = "The setting you want to see"] key: ConfigKey,
+) -> Result<()> {
+    let guild_id = ctx.guild_id().expect("/config get was not run in a guild");
+    let config = ctx.data().guild_config(guild_id).await;
+
+    bail_reply(ctx, format!("`{}` is {}", key.name(), config.get(key))).await
+}
+
+/// ADMIN ONLY: Change a setting for this server
+#[poise::command(guild_only, slash_command)]
+async fn set(
+    ctx: Context<'_>,
+    #[description = "The setting you want to change"] key: ConfigKey,
+    #[description = "A role/channel mention or id, seconds, a percentage, some text or `none`"]
+    value: String,
+) -> Result<()> {
+    let guild_id = ctx.guild_id().expect("/config set was not run in a guild");
+
+    if let Err(e) = save_guild_config_value(ctx.data(), guild_id, key, &value).await {
+        return bail_reply(ctx, e.to_string()).await;
+    }
+
+    let config = ctx.data().guild_config(guild_id).await;
+    ctx.send(ephemeral_reply(format!(
+        "`{}` has been set to {}",
+        key.name(),
+        config.get(key)
+    )))
+    .await?;
+
+    Ok(())
+}
+
+/// ADMIN ONLY: List every setting for this server
+#[poise::command(guild_only, slash_command)]
+async fn list(ctx: Context<'_>) -> Result<()> {
+    let guild_id = ctx.guild_id().expect("/config list was not run in a guild");
+    let config = ctx.data().guild_config(guild_id).await;
+
+    let description = ConfigKey::all()
+        .map(|key| format!("`{}`: {}", key.name(), config.get(key)))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let embed = CreateEmbed::default()
+        .colour(0x5865F2)
+        .title("Server settings")
+        .description(description);
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
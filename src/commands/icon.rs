@@ -1,13 +1,17 @@
 use serenity::all::{EditRole, Emoji, GuildId, Member, RoleId};
 
-use crate::{common::bail_reply, Context, Result};
+use super::role_panel::{post_role_panel, PanelKind};
+use crate::{
+    common::{autocomplete_suffixed_roles, bail_reply},
+    Context, Result,
+};
 
-const ROLE_SUFFIX: &str = "[ICON]";
+pub const ROLE_SUFFIX: &str = "[ICON]";
 
 #[poise::command(
     guild_only,
     slash_command,
-    subcommands("create", "delete"),
+    subcommands("create", "delete", "panel"),
     required_permissions = "ADMINISTRATOR",
     default_member_permissions = "ADMINISTRATOR"
 )]
@@ -19,7 +23,9 @@ pub async fn icon(_ctx: Context<'_>) -> Result<()> {
 #[poise::command(guild_only, slash_command)]
 async fn create(
     ctx: Context<'_>,
-    #[description = "An emote from this server"] emote: String,
+    #[description = "An emote from this server"]
+    #[autocomplete = "autocomplete_server_emojis"]
+    emote: String,
 ) -> Result<()> {
     let guild_id = ctx.guild_id().expect("/icon create was not run in a guild");
 
@@ -60,7 +66,9 @@ async fn create(
 #[poise::command(guild_only, slash_command)]
 async fn delete(
     ctx: Context<'_>,
-    #[description = "An emote from this server"] emote: String,
+    #[description = "An emote from this server"]
+    #[autocomplete = "autocomplete_icon_roles"]
+    emote: String,
 ) -> Result<()> {
     let guild_id = ctx.guild_id().expect("/icon delete was not run in a guild");
 
@@ -80,11 +88,19 @@ async fn delete(
     Ok(())
 }
 
+/// MOD ONLY: Post a message with a button for every icon role
+#[poise::command(guild_only, slash_command)]
+async fn panel(ctx: Context<'_>) -> Result<()> {
+    post_role_panel(ctx, PanelKind::Icons).await
+}
+
 /// Add/Remove an icon role from your roles
 #[poise::command(guild_only, slash_command)]
 pub async fn iconsub(
     ctx: Context<'_>,
-    #[description = "Select the emoji for the role you want to join"] emote: String,
+    #[description = "Select the emoji for the role you want to join"]
+    #[autocomplete = "autocomplete_icon_roles"]
+    emote: String,
 ) -> Result<()> {
     let guild_id = ctx.guild_id().expect("/iconsub was not run on a guild");
     let role_name = to_role_name(&emote);
@@ -148,3 +164,33 @@ async fn get_member_icon_roles(ctx: Context<'_>, member: &Member) -> Option<Vec<
 
     Some(roles)
 }
+
+async fn autocomplete_icon_roles<'a>(
+    ctx: Context<'a>,
+    partial: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+    autocomplete_suffixed_roles(ctx, partial, ROLE_SUFFIX).await
+}
+
+async fn autocomplete_server_emojis<'a>(
+    ctx: Context<'a>,
+    partial: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+    let emojis = match ctx.guild_id() {
+        Some(guild_id) => guild_id.emojis(ctx).await.unwrap_or_else(|e| {
+            eprintln!("Error while trying to suggest autocomplete for '{partial}': {e}");
+            vec![]
+        }),
+        None => vec![],
+    };
+
+    let partial = partial.to_lowercase();
+    let mut names = emojis
+        .into_iter()
+        .map(|e| e.name)
+        .filter(|name| name.to_lowercase().contains(&partial))
+        .collect::<Vec<_>>();
+    names.sort();
+
+    names.into_iter().take(25)
+}
@@ -1,6 +1,6 @@
 use std::sync::atomic::{AtomicI64, Ordering};
 
-use crate::{common::bail_reply, config::MIXU_CHANNEL, Context, Result};
+use crate::{common::bail_reply, Context, Result};
 
 use poise::serenity_prelude::{Emoji, Mention, UserId};
 use rand::{seq::SliceRandom, thread_rng};
@@ -31,8 +31,12 @@ pub async fn set_initial_best_mixu_score(db: &SqlitePool) -> Result<()> {
 /// Generate a random mixu
 #[poise::command(guild_only, slash_command, prefix_command)]
 pub async fn mixu(ctx: Context<'_>) -> Result<()> {
-    if ctx.channel_id() != MIXU_CHANNEL {
-        let msg = format!("You can only use this command in {}", MIXU_CHANNEL);
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected Mixu commands to be guild only");
+    let mixu_channel = ctx.data().guild_config(guild_id).await.mixu_channel;
+    if ctx.channel_id() != mixu_channel {
+        let msg = format!("You can only use this command in {}", mixu_channel);
         return bail_reply(ctx, msg).await;
     }
 
@@ -1,26 +1,33 @@
 mod ask;
+mod call_response;
 mod colors;
 mod dino;
 mod duel;
 mod dynamic_commands;
 mod eightball;
 mod embedpls;
+mod greeter;
+mod guild_config;
 mod icon;
 mod itad;
 mod mixu;
+mod modlog;
 mod poll;
 mod quote;
 mod rockpaperscissors;
+mod role_panel;
 mod rolesub;
 mod roll;
 mod rpg;
 mod timeout;
+mod uploads;
 mod uwu;
+mod warn;
 
 use crate::{Data, Error};
 use dino::setup_dinos;
-use dynamic_commands::CommandInfo;
-use poise::serenity_prelude::{all::CreateCommand, Context as SerenityContext};
+use dynamic_commands::{build_command, CommandInfo, CommandRestrictions, LastUses};
+use poise::serenity_prelude::{Context as SerenityContext, RoleId};
 use poise::Command;
 use std::{collections::HashMap, sync::OnceLock};
 
@@ -32,6 +39,10 @@ pub async fn setup_collectors(ctx: &SerenityContext, user_data: &Data) {
     tokio::select! {
         _ = rpg::setup_rpg_summary(ctx, user_data) => {}
         _ = dino::setup_dino_collector(ctx, user_data) => {}
+        _ = dino::setup_market_collector(ctx, user_data) => {}
+        _ = poll::setup_poll_collector(ctx, user_data) => {}
+        _ = poll::setup_poll_scheduler(ctx, user_data) => {}
+        _ = role_panel::setup_role_panel_collector(ctx, user_data) => {}
     }
 }
 
@@ -54,6 +65,7 @@ pub async fn register_dynamic_commands_for_every_guild(ctx: &SerenityContext, us
 
 pub async fn initialize_commands(database: &sqlx::SqlitePool) -> Vec<Command<Data, Error>> {
     let mut commands = vec![
+        call_response::callresponse(),
         colors::color(),
         colors::uncolor(),
         duel::duel(),
@@ -61,8 +73,11 @@ pub async fn initialize_commands(database: &sqlx::SqlitePool) -> Vec<Command<Dat
         eightball::eightball(),
         eightball::fball(),
         embedpls::embedpls(),
+        greeter::greeter(),
+        guild_config::config(),
         mixu::mikustare(),
-        // poll::poll(), // omit this for now
+        modlog::modlog(),
+        poll::poll(),
         quote::quote(),
         quote::quwuote(),
         rockpaperscissors::rps(),
@@ -72,10 +87,12 @@ pub async fn initialize_commands(database: &sqlx::SqlitePool) -> Vec<Command<Dat
         timeout::sudoku(),
         timeout::timeout(),
         uwu::uwu(),
+        warn::warn(),
         icon::icon(),       // Mod commands for icon
         icon::iconsub(),    // Add/remove icon role
         rolesub::role(),    // Mod commands for rolesub
         rolesub::rolesub(), // Add/remove bot role
+        rolesub::roles(),   // List bot roles
     ];
 
     match ask::initialize_app_id() {
@@ -100,7 +117,7 @@ pub async fn initialize_commands(database: &sqlx::SqlitePool) -> Vec<Command<Dat
         ),
     }
 
-    match setup_dinos() {
+    match setup_dinos(database).await {
         Ok(_) => {
             commands.push(dino::dino());
             commands.push(dino::dinomod());
@@ -117,12 +134,22 @@ struct GuildCommand {
     name: String,
     kind: CommandKind,
     content: String,
+    description: Option<String>,
+    title: Option<String>,
+    colour: Option<i64>,
+    image: Option<String>,
+    cooldown: i64,
+    user_cooldown: i64,
+    allowed_channels: Option<String>,
+    required_role: Option<i64>,
 }
 
 async fn fetch_guild_commands(user_data: &Data) -> anyhow::Result<SimpleCommands> {
     let guild_commands = sqlx::query_as!(
         GuildCommand,
-        r#"SELECT guild_id, name, kind as "kind: CommandKind", content FROM SimpleCommands"#
+        r#"SELECT guild_id, name, kind as "kind: CommandKind", content, description, title, colour, image,
+            cooldown, user_cooldown, allowed_channels, required_role
+        FROM SimpleCommands"#
     )
     .fetch_all(&user_data.database)
     .await?;
@@ -133,6 +160,22 @@ async fn fetch_guild_commands(user_data: &Data) -> anyhow::Result<SimpleCommands
         let info = CommandInfo {
             kind: command.kind,
             content: command.content,
+            description: command.description,
+            title: command.title,
+            colour: command.colour,
+            image: command.image,
+            restrictions: CommandRestrictions {
+                cooldown: command.cooldown,
+                user_cooldown: command.user_cooldown,
+                allowed_channels: command
+                    .allowed_channels
+                    .as_deref()
+                    .map(CommandRestrictions::parse_channels)
+                    .transpose()?
+                    .unwrap_or_default(),
+                required_role: command.required_role.map(|r| RoleId::new(r as u64)),
+            },
+            last_uses: LastUses::default(),
         };
 
         entry.insert(command.name, info);
@@ -146,15 +189,15 @@ async fn register_guild_commands(
     commands_map: &SimpleCommands,
 ) -> anyhow::Result<()> {
     for id in ctx.cache.guilds() {
-        let Some(names) = commands_map.get(&(id.get() as i64)) else {
+        let Some(guild_commands) = commands_map.get(&(id.get() as i64)) else {
             // Reset commands if there aren't any for this guild
             id.set_commands(&ctx.http, Vec::new()).await?;
             continue;
         };
 
-        let commands = names
+        let commands = guild_commands
             .iter()
-            .map(|(name, _content)| CreateCommand::new(name).description("A simple text command"))
+            .map(|(name, command)| build_command(name, command))
             .collect::<Vec<_>>();
 
         id.set_commands(ctx, commands).await?;
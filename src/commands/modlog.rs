@@ -0,0 +1,239 @@
+use chrono::{TimeDelta, Utc};
+use poise::serenity_prelude::{CreateEmbed, CreateEmbedFooter, CreateMessage, Member, UserId};
+use poise::CreateReply;
+
+use crate::{
+    common::{avatar_url, bail_reply, truncate_lines},
+    Context, Result,
+};
+
+const MAX_LISTED_ACTIONS: i64 = 15;
+/// The most text Discord allows in an embed description and in a field
+const MAX_DESCRIPTION_LENGTH: usize = 4096;
+const MAX_FIELD_LENGTH: usize = 1024;
+
+/// What caused a moderation action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+pub enum ModerationSource {
+    Timeout,
+    Sudoku,
+    DuelDraw,
+    Pardon,
+    Warning,
+}
+
+impl ModerationSource {
+    fn title(self) -> &'static str {
+        match self {
+            ModerationSource::Timeout => "Timeout",
+            ModerationSource::Sudoku => "Sudoku",
+            ModerationSource::DuelDraw => "Duel draw",
+            ModerationSource::Pardon => "Pardon",
+            ModerationSource::Warning => "Warning",
+        }
+    }
+
+    fn colour(self) -> u32 {
+        match self {
+            ModerationSource::Pardon => 0x57F287,
+            ModerationSource::Sudoku | ModerationSource::DuelDraw | ModerationSource::Warning => {
+                0xFEE75C
+            }
+            ModerationSource::Timeout => 0xED4245,
+        }
+    }
+}
+
+pub struct ModerationAction {
+    pub actor: UserId,
+    pub target: UserId,
+    pub reason: Option<String>,
+    pub duration: Option<TimeDelta>,
+    pub source: ModerationSource,
+}
+
+struct RecordedAction {
+    actor_id: i64,
+    reason: Option<String>,
+    duration: Option<i64>,
+    source: ModerationSource,
+    created_at: i64,
+}
+
+/// Keep a record of the action and post it in the mod log channel if the server has one.
+pub async fn record_action(ctx: Context<'_>, action: ModerationAction) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected moderation actions to happen in a guild");
+
+    let db_guild_id = guild_id.get() as i64;
+    let actor_id = action.actor.get() as i64;
+    let target_id = action.target.get() as i64;
+    let duration = action.duration.map(|d| d.num_seconds());
+    let now = Utc::now();
+    let created_at = now.timestamp();
+    sqlx::query!(
+        "INSERT INTO ModerationAction
+            (guild_id, actor_id, target_id, reason, duration, source, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?)",
+        db_guild_id,
+        actor_id,
+        target_id,
+        action.reason,
+        duration,
+        action.source,
+        created_at
+    )
+    .execute(&ctx.data().database)
+    .await?;
+
+    let config = ctx.data().guild_config(guild_id).await;
+    let Some(channel) = config.mod_log_channel else {
+        return Ok(());
+    };
+
+    let mut embed = CreateEmbed::default()
+        .colour(action.source.colour())
+        .title(action.source.title())
+        .field("Member", format!("<@{}>", action.target), true)
+        .field("By", format!("<@{}>", action.actor), true)
+        .timestamp(now);
+    if let Some(duration) = action.duration {
+        embed = embed.field("Duration", format_duration(duration.num_seconds()), true);
+    }
+    if let Some(reason) = &action.reason {
+        // Prefix commands don't enforce the length limit of the reason
+        let reason = reason.chars().take(MAX_FIELD_LENGTH).collect::<String>();
+        embed = embed.field("Reason", reason, false);
+    }
+
+    channel
+        .send_message(ctx, CreateMessage::new().embed(embed))
+        .await?;
+
+    Ok(())
+}
+
+/// Log a moderation action, a failure to do so shouldn't stop the command that caused it.
+pub async fn try_record_action(ctx: Context<'_>, action: ModerationAction) {
+    if let Err(e) = record_action(ctx, action).await {
+        eprintln!("Failed to record moderation action: {e:?}");
+    }
+}
+
+#[poise::command(
+    guild_only,
+    slash_command,
+    subcommands("user"),
+    required_permissions = "MODERATE_MEMBERS",
+    default_member_permissions = "MODERATE_MEMBERS"
+)]
+pub async fn modlog(_ctx: Context<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// MOD ONLY: Show the moderation history of a member
+#[poise::command(guild_only, slash_command)]
+async fn user(
+    ctx: Context<'_>,
+    #[description = "The member whose history you want to see"] member: Member,
+) -> Result<()> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /modlog user to be guild only.")
+        .get() as i64;
+    let target_id = member.user.id.get() as i64;
+
+    let actions = sqlx::query_as!(
+        RecordedAction,
+        r#"SELECT actor_id AS "actor_id!", reason, duration,
+            source AS "source!: ModerationSource", created_at AS "created_at!"
+        FROM ModerationAction
+        WHERE guild_id = ? AND target_id = ?
+        ORDER BY created_at DESC, id DESC
+        LIMIT ?"#,
+        guild_id,
+        target_id,
+        MAX_LISTED_ACTIONS
+    )
+    .fetch_all(&ctx.data().database)
+    .await?;
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!: i64" FROM ModerationAction
+        WHERE guild_id = ? AND target_id = ?"#,
+        guild_id,
+        target_id
+    )
+    .fetch_one(&ctx.data().database)
+    .await?;
+
+    if actions.is_empty() {
+        return bail_reply(ctx, format!("{member} has a clean record.")).await;
+    }
+
+    let lines = actions.iter().map(describe_action).collect::<Vec<_>>();
+    let description = truncate_lines(&lines, MAX_DESCRIPTION_LENGTH);
+    let embed = CreateEmbed::default()
+        .colour(0x5865F2)
+        .title(format!("History of {}", member.display_name()))
+        .thumbnail(avatar_url(&member.user))
+        .description(description)
+        .footer(CreateEmbedFooter::new(format!(
+            "Showing {} of {total} actions",
+            actions.len()
+        )));
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+fn describe_action(action: &RecordedAction) -> String {
+    let when = format!("<t:{}:d>", action.created_at);
+    let duration = action
+        .duration
+        .map(|d| format!(" for {}", format_duration(d)))
+        .unwrap_or_default();
+    let reason = action
+        .reason
+        .as_deref()
+        .map(|r| format!(": {r}"))
+        .unwrap_or_default();
+
+    format!(
+        "{when} **{}**{duration} by <@{}>{reason}",
+        action.source.title(),
+        action.actor_id
+    )
+}
+
+pub fn format_duration(seconds: i64) -> String {
+    let (days, hours) = (seconds / 86400, seconds % 86400 / 3600);
+    let (minutes, seconds) = (seconds % 3600 / 60, seconds % 60);
+
+    let parts = [(days, "d"), (hours, "h"), (minutes, "m"), (seconds, "s")]
+        .into_iter()
+        .filter(|&(amount, _)| amount > 0)
+        .map(|(amount, unit)| format!("{amount}{unit}"))
+        .collect::<Vec<_>>();
+
+    if parts.is_empty() {
+        "0s".to_string()
+    } else {
+        parts.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format_duration(0), "0s");
+        assert_eq!(format_duration(59), "59s");
+        assert_eq!(format_duration(3600), "1h");
+        assert_eq!(format_duration(90061), "1d 1h 1m 1s");
+    }
+}
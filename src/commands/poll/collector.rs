@@ -0,0 +1,320 @@
+use poise::futures_util::StreamExt;
+use poise::serenity_prelude::{
+    self as serenity, ComponentInteraction, ComponentInteractionCollector,
+    ComponentInteractionDataKind, CreateInteractionResponseMessage,
+};
+use sqlx::SqlitePool;
+
+use crate::common::{ephemeral_text_message, response, update_response};
+use crate::Data;
+use crate::Result;
+
+use super::{
+    close_expired_poll, fetch_choices, fetch_poll, rank_menu, refresh_poll_message, PollRecord,
+    PICK_MENU, RANK_BUTTON, RANK_MENU, VOTE_BUTTON,
+};
+
+pub async fn setup_poll_collector(ctx: &serenity::Context, user_data: &Data) -> Result<()> {
+    let mut collector = ComponentInteractionCollector::new(ctx)
+        .filter(|f| {
+            let prefix = f.data.custom_id.split(':').next();
+            [VOTE_BUTTON, PICK_MENU, RANK_BUTTON, RANK_MENU]
+                .iter()
+                .any(|&p| prefix == Some(p))
+        })
+        .stream();
+
+    println!("Setup poll collector");
+
+    while let Some(interaction) = collector.next().await {
+        if let Err(e) = handle_ballot(ctx, user_data, &interaction).await {
+            eprintln!("Error while handling poll vote: {e}");
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_ballot(
+    ctx: &serenity::Context,
+    user_data: &Data,
+    interaction: &ComponentInteraction,
+) -> Result<()> {
+    let db = &user_data.database;
+    let mut parts = interaction.data.custom_id.split(':');
+    let prefix = parts.next().unwrap_or_default();
+    let ids = parts
+        .map(|id| id.parse::<i64>().ok())
+        .collect::<Option<Vec<_>>>();
+
+    let (poll_id, argument) = match (prefix, ids.as_deref()) {
+        (RANK_MENU, Some(&[poll_id, rank])) => (poll_id, rank),
+        (VOTE_BUTTON, Some(&[choice_id])) => (interaction.message.id.get() as i64, choice_id),
+        (PICK_MENU | RANK_BUTTON, Some(&[poll_id])) => (poll_id, 0),
+        _ => {
+            let resp = response(ephemeral_text_message("This poll button is not valid."));
+            interaction.create_response(ctx, resp).await?;
+            return Ok(());
+        }
+    };
+
+    let poll = fetch_poll(db, poll_id).await?;
+    let Some(poll) = poll.filter(|p| !p.closed) else {
+        let resp = response(ephemeral_text_message("This poll is closed."));
+        interaction.create_response(ctx, resp).await?;
+        return Ok(());
+    };
+
+    if poll.is_past_deadline() {
+        let resp = response(ephemeral_text_message(
+            "Sorry, voting for this poll has ended.",
+        ));
+        interaction.create_response(ctx, resp).await?;
+        close_expired_poll(ctx, db, &poll).await?;
+        return Ok(());
+    }
+
+    match prefix {
+        VOTE_BUTTON => handle_vote(ctx, db, interaction, &poll, argument).await?,
+        PICK_MENU => handle_picks(ctx, db, interaction, &poll).await?,
+        RANK_BUTTON => start_ranking(ctx, db, interaction, &poll).await?,
+        _ => handle_rank(ctx, db, interaction, &poll, argument).await?,
+    }
+
+    refresh_poll_message(ctx, db, &poll).await
+}
+
+async fn handle_vote(
+    ctx: &serenity::Context,
+    db: &SqlitePool,
+    interaction: &ComponentInteraction,
+    poll: &PollRecord,
+    choice_id: i64,
+) -> Result<()> {
+    let user_id = interaction.user.id.get() as i64;
+    let Some(choice) = sqlx::query!(
+        "SELECT text FROM PollChoice WHERE id = ? AND poll_id = ?",
+        choice_id,
+        poll.message_id
+    )
+    .fetch_optional(db)
+    .await?
+    else {
+        let resp = response(ephemeral_text_message(
+            "That choice was removed from the poll.",
+        ));
+        interaction.create_response(ctx, resp).await?;
+        return Ok(());
+    };
+
+    // Pressing the button of the choice you already voted for takes the vote back
+    let retracted = sqlx::query!(
+        "DELETE FROM PollVote WHERE poll_id = ? AND user_id = ? AND choice_id = ?",
+        poll.message_id,
+        user_id,
+        choice_id
+    )
+    .execute(db)
+    .await?
+    .rows_affected()
+        > 0;
+
+    let content = if retracted {
+        format!("Your vote for **{}** has been removed.", choice.text)
+    } else {
+        let mut transaction = db.begin().await?;
+        sqlx::query!(
+            "DELETE FROM PollVote WHERE poll_id = ? AND user_id = ?",
+            poll.message_id,
+            user_id
+        )
+        .execute(&mut transaction)
+        .await?;
+        sqlx::query!(
+            "INSERT INTO PollVote (poll_id, user_id, choice_id) VALUES (?, ?, ?)",
+            poll.message_id,
+            user_id,
+            choice_id
+        )
+        .execute(&mut transaction)
+        .await?;
+        transaction.commit().await?;
+
+        format!("You voted for **{}**.", choice.text)
+    };
+
+    interaction
+        .create_response(ctx, response(ephemeral_text_message(content)))
+        .await?;
+
+    Ok(())
+}
+
+/// Replace someone's multi-select ballot with the choices they picked in the menu.
+async fn handle_picks(
+    ctx: &serenity::Context,
+    db: &SqlitePool,
+    interaction: &ComponentInteraction,
+    poll: &PollRecord,
+) -> Result<()> {
+    let user_id = interaction.user.id.get() as i64;
+    let picks = selected_choices(interaction);
+    let choices = fetch_choices(db, poll.message_id).await?;
+    let picked = choices
+        .iter()
+        .filter(|c| picks.contains(&c.id))
+        .take(poll.max_picks as usize)
+        .collect::<Vec<_>>();
+
+    let mut transaction = db.begin().await?;
+    sqlx::query!(
+        "DELETE FROM PollVote WHERE poll_id = ? AND user_id = ?",
+        poll.message_id,
+        user_id
+    )
+    .execute(&mut transaction)
+    .await?;
+    for choice in &picked {
+        sqlx::query!(
+            "INSERT INTO PollVote (poll_id, user_id, choice_id) VALUES (?, ?, ?)",
+            poll.message_id,
+            user_id,
+            choice.id
+        )
+        .execute(&mut transaction)
+        .await?;
+    }
+    transaction.commit().await?;
+
+    let content = if picked.is_empty() {
+        "Your votes have been removed.".to_string()
+    } else {
+        let names = picked
+            .iter()
+            .map(|c| format!("**{}**", c.text))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("You voted for {names}.")
+    };
+
+    interaction
+        .create_response(ctx, response(ephemeral_text_message(content)))
+        .await?;
+
+    Ok(())
+}
+
+async fn start_ranking(
+    ctx: &serenity::Context,
+    db: &SqlitePool,
+    interaction: &ComponentInteraction,
+    poll: &PollRecord,
+) -> Result<()> {
+    let choices = fetch_choices(db, poll.message_id).await?;
+    let message = CreateInteractionResponseMessage::new()
+        .content(format!(
+            "Rank the choices for **{}**, picking your favourite first. Picking a new #1 starts your ballot over.",
+            poll.question
+        ))
+        .components(vec![rank_menu(poll, &choices, 1)])
+        .ephemeral(true);
+
+    interaction.create_response(ctx, response(message)).await?;
+
+    Ok(())
+}
+
+/// Put the picked choice at `rank` on someone's ballot and ask for the next one.
+async fn handle_rank(
+    ctx: &serenity::Context,
+    db: &SqlitePool,
+    interaction: &ComponentInteraction,
+    poll: &PollRecord,
+    rank: i64,
+) -> Result<()> {
+    let user_id = interaction.user.id.get() as i64;
+    let choices = fetch_choices(db, poll.message_id).await?;
+    let Some(choice) = selected_choices(interaction)
+        .first()
+        .and_then(|&id| choices.iter().find(|c| c.id == id))
+    else {
+        let resp = update_response(
+            CreateInteractionResponseMessage::new()
+                .content("That choice was removed from the poll, start over with the button.")
+                .components(Vec::new()),
+        );
+        interaction.create_response(ctx, resp).await?;
+        return Ok(());
+    };
+
+    // Going back to an earlier rank throws away everything ranked after it
+    let mut transaction = db.begin().await?;
+    sqlx::query!(
+        "DELETE FROM PollVote WHERE poll_id = ? AND user_id = ? AND (rank >= ? OR choice_id = ?)",
+        poll.message_id,
+        user_id,
+        rank,
+        choice.id
+    )
+    .execute(&mut transaction)
+    .await?;
+    sqlx::query!(
+        "INSERT INTO PollVote (poll_id, user_id, choice_id, rank) VALUES (?, ?, ?, ?)",
+        poll.message_id,
+        user_id,
+        choice.id,
+        rank
+    )
+    .execute(&mut transaction)
+    .await?;
+    let ranked = sqlx::query!(
+        "SELECT choice_id FROM PollVote WHERE poll_id = ? AND user_id = ? ORDER BY rank",
+        poll.message_id,
+        user_id
+    )
+    .fetch_all(&mut transaction)
+    .await?
+    .into_iter()
+    .map(|r| r.choice_id)
+    .collect::<Vec<_>>();
+    transaction.commit().await?;
+
+    let ballot = ranked
+        .iter()
+        .filter_map(|id| choices.iter().find(|c| c.id == *id))
+        .enumerate()
+        .map(|(i, c)| format!("{}. {} {}", i + 1, c.icon, c.text))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let (unranked, ranked_choices): (Vec<_>, Vec<_>) =
+        choices.into_iter().partition(|c| !ranked.contains(&c.id));
+
+    let message = CreateInteractionResponseMessage::new();
+    let message = if unranked.is_empty() {
+        message
+            .content(format!("Your ballot is complete:\n{ballot}"))
+            .components(Vec::new())
+    } else {
+        let next_rank = ranked_choices.len() as i64 + 1;
+        message
+            .content(format!(
+                "Your ballot so far, it already counts:\n{ballot}\n\nKeep going or dismiss this message when you're done."
+            ))
+            .components(vec![rank_menu(poll, &unranked, next_rank)])
+    };
+    interaction
+        .create_response(ctx, update_response(message))
+        .await?;
+
+    Ok(())
+}
+
+fn selected_choices(interaction: &ComponentInteraction) -> Vec<i64> {
+    match &interaction.data.kind {
+        ComponentInteractionDataKind::StringSelect { values } => values
+            .iter()
+            .filter_map(|value| value.parse().ok())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
@@ -0,0 +1,912 @@
+use crate::common::{bail_reply, ephemeral_reply, truncate_lines};
+use crate::{Context, Result};
+
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use poise::serenity_prelude::{
+    ButtonStyle, CacheHttp, ChannelId, CreateActionRow, CreateButton, CreateEmbed,
+    CreateEmbedFooter, CreateMessage, CreateSelectMenu, CreateSelectMenuKind,
+    CreateSelectMenuOption, EditMessage, Message, MessageId, ReactionType, Role,
+};
+use poise::CreateReply;
+use serenity::all::AutocompleteChoice;
+use sqlx::{SqliteExecutor, SqlitePool};
+
+use super::runoff::{instant_runoff, Runoff};
+
+pub const ICONS_LEN: usize = 20;
+const ICONS: [&str; ICONS_LEN] = [
+    "📐", "💡", "🔍", "🎬", "📷", "📞", "🔫", "🏹", "🥊", "🔮", "👑", "🎩", "🎪", "🎨", "🎄", "🎀",
+    "🎃", "🎊", "🎉", "✨",
+];
+
+pub const VOTE_BUTTON: &str = "poll-vote";
+pub const PICK_MENU: &str = "poll-pick";
+pub const RANK_BUTTON: &str = "poll-rank";
+pub const RANK_MENU: &str = "poll-rank-menu";
+
+pub const REMINDER_BEFORE_CLOSE: TimeDelta = TimeDelta::hours(1);
+
+const BUTTONS_PER_ROW: usize = 5;
+const BAR_LENGTH: usize = 20;
+/// Discord rejects embeds with longer titles, descriptions or field values.
+const MAX_TITLE_LENGTH: usize = 256;
+const MAX_DESCRIPTION_LENGTH: usize = 4096;
+const MAX_FIELD_LENGTH: usize = 1024;
+
+#[derive(Debug, sqlx::Type, poise::ChoiceParameter, Clone, Copy, PartialEq, Eq)]
+pub enum PollKind {
+    #[name = "Single choice"]
+    Single,
+    #[name = "Multi-select"]
+    Multi,
+    #[name = "Ranked choice"]
+    Ranked,
+}
+
+pub struct PollRecord {
+    pub message_id: i64,
+    pub channel_id: i64,
+    pub question: String,
+    pub closes_at: Option<NaiveDateTime>,
+    pub closed: bool,
+    pub reminder_role_id: Option<i64>,
+    pub kind: PollKind,
+    pub max_picks: i64,
+}
+
+struct PollSettings {
+    kind: PollKind,
+    max_picks: i64,
+    closes_at: Option<NaiveDateTime>,
+    reminder_role_id: Option<i64>,
+}
+
+impl PollRecord {
+    pub fn is_past_deadline(&self) -> bool {
+        self.closes_at
+            .is_some_and(|closes_at| closes_at <= Utc::now().naive_utc())
+    }
+}
+
+pub struct ChoiceRecord {
+    pub id: i64,
+    pub icon: String,
+    pub owner_id: i64,
+    pub text: String,
+    pub votes: i64,
+}
+
+pub struct PollResults {
+    pub choices: Vec<ChoiceRecord>,
+    pub voters: i64,
+    /// Only ranked polls are counted with a runoff
+    pub runoff: Option<Runoff>,
+}
+
+#[poise::command(
+    guild_only,
+    slash_command,
+    subcommands("new", "close", "choice", "whoops")
+)]
+pub async fn poll(_ctx: Context<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Start a new poll
+#[poise::command(guild_only, slash_command, required_permissions = "MANAGE_MESSAGES")]
+async fn new(
+    ctx: Context<'_>,
+    #[description = "What you want to ask"]
+    #[max_length = 200]
+    question: String,
+    #[description = "The initial choices separated by |"] choices: Option<String>,
+    #[description = "Stop accepting votes after this many minutes"]
+    #[min = 1]
+    #[max = 10080] // a week
+    duration: Option<u32>,
+    #[description = "The role to ping an hour before the poll closes"] reminder: Option<Role>,
+    #[description = "How people vote, a single choice by default"] kind: Option<PollKind>,
+    #[description = "How many choices people can pick in a multi-select poll"]
+    #[min = 2]
+    #[max = 20]
+    max_picks: Option<u8>,
+) -> Result<()> {
+    if reminder.is_some() && duration.is_none() {
+        return bail_reply(ctx, "A poll needs a duration to send a reminder.").await;
+    }
+
+    let kind = kind.unwrap_or(PollKind::Single);
+    let max_picks = match (kind, max_picks) {
+        (PollKind::Multi, picks) => picks.map_or(ICONS_LEN as i64, i64::from),
+        (_, None) => 1,
+        (_, Some(_)) => {
+            let msg = "Only multi-select polls can have more than one pick.";
+            return bail_reply(ctx, msg).await;
+        }
+    };
+
+    let choices = choices
+        .as_deref()
+        .unwrap_or_default()
+        .split('|')
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .collect::<Vec<_>>();
+
+    if choices.len() > ICONS_LEN {
+        let msg = format!("A poll can have at most {ICONS_LEN} choices.");
+        return bail_reply(ctx, msg).await;
+    }
+    if let Some(choice) = choices.iter().find(|c| c.chars().count() > 25) {
+        let msg = format!("`{choice}` is too long, choices can have at most 25 characters.");
+        return bail_reply(ctx, msg).await;
+    }
+
+    let embed = CreateEmbed::new()
+        .title(&question)
+        .description("Setting up the poll...");
+    let reply = ctx.send(CreateReply::default().embed(embed)).await?;
+    let message = reply.into_message().await?;
+
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /poll new to be guild only.");
+    let duration = duration.map(|minutes| TimeDelta::minutes(minutes.into()));
+    let closes_at = duration.map(|duration| Utc::now().naive_utc() + duration);
+    // Short polls would be reminded about as soon as they're created
+    let reminder = reminder.filter(|_| duration > Some(REMINDER_BEFORE_CLOSE));
+
+    let mut transaction = ctx.data().database.begin().await?;
+    let settings = PollSettings {
+        kind,
+        max_picks,
+        closes_at,
+        reminder_role_id: reminder.map(|r| r.id.get() as i64),
+    };
+    let poll = insert_poll(
+        &mut transaction,
+        &message,
+        guild_id.get() as i64,
+        ctx.author().id.get() as i64,
+        &question,
+        &settings,
+    )
+    .await?;
+    for (icon, choice) in ICONS.iter().zip(choices) {
+        insert_choice(
+            &mut transaction,
+            poll.message_id,
+            icon,
+            ctx.author().id.get() as i64,
+            choice,
+        )
+        .await?;
+    }
+    transaction.commit().await?;
+
+    refresh_poll_message(ctx, &ctx.data().database, &poll).await?;
+
+    Ok(())
+}
+
+#[derive(poise::ChoiceParameter)]
+enum CloseKind {
+    Announce,
+    Silent,
+}
+
+/// Close a poll and show its results
+#[poise::command(guild_only, slash_command, required_permissions = "MANAGE_MESSAGES")]
+async fn close(
+    ctx: Context<'_>,
+    #[description = "The poll you want to close"]
+    #[autocomplete = "autocomplete_open_polls"]
+    poll: String,
+    #[description = "Whether to announce the winner or not"] kind: Option<CloseKind>,
+) -> Result<()> {
+    let db = &ctx.data().database;
+    let Some(found_poll) = find_open_poll(ctx, &poll).await? else {
+        return bail_reply(ctx, "There's no poll to close").await;
+    };
+
+    let results = close_poll(ctx, db, &found_poll).await?;
+
+    let kind = kind.unwrap_or(CloseKind::Silent);
+    let reply = match (kind, winner_embed(&found_poll, &results)) {
+        (CloseKind::Silent, _) => ephemeral_reply("The poll has been closed!"),
+        (CloseKind::Announce, Some(embed)) => CreateReply::default().embed(embed),
+        (CloseKind::Announce, None) => {
+            ephemeral_reply("There were no choices for this poll, I closed it now though.")
+        }
+    };
+    ctx.send(reply).await?;
+
+    Ok(())
+}
+
+fn winner_embed(poll: &PollRecord, results: &PollResults) -> Option<CreateEmbed> {
+    let choices = &results.choices;
+    if choices.is_empty() {
+        return None;
+    }
+
+    let title = match &results.runoff {
+        None => {
+            let most_votes = choices.iter().map(|c| c.votes).max().unwrap_or_default();
+            let winners = choices
+                .iter()
+                .filter(|c| c.votes == most_votes)
+                .collect::<Vec<_>>();
+            match winners.as_slice() {
+                _ if most_votes == 0 => format!("Nobody voted on {}", poll.question),
+                [winner] => format!(
+                    "{} winner: {} {} with {} votes!",
+                    poll.question, winner.icon, winner.text, winner.votes
+                ),
+                tied => format!(
+                    "{} ended in a tie between {}!",
+                    poll.question,
+                    tied.iter()
+                        .map(|c| format!("{} {}", c.icon, c.text))
+                        .collect::<Vec<_>>()
+                        .join(" and ")
+                ),
+            }
+        }
+        Some(runoff) => match runoff.winners.as_slice() {
+            [] => format!("Nobody voted on {}", poll.question),
+            [winner] => format!(
+                "{} winner: {} after {} rounds!",
+                poll.question,
+                choice_label(choices, *winner),
+                runoff.rounds.len()
+            ),
+            tied => format!(
+                "{} ended in a tie between {}!",
+                poll.question,
+                tied.iter()
+                    .map(|&id| choice_label(choices, id))
+                    .collect::<Vec<_>>()
+                    .join(" and ")
+            ),
+        },
+    };
+
+    Some(results_embed(poll, results).title(fit_title(title)))
+}
+
+/// Add a choice to a poll
+#[poise::command(guild_only, slash_command)]
+async fn choice(
+    ctx: Context<'_>,
+    #[description = "The poll you want to add a choice to"]
+    #[autocomplete = "autocomplete_open_polls"]
+    poll: String,
+    #[description = "The choice you want to add to the poll"]
+    #[max_length = 25]
+    choice: String,
+) -> Result<()> {
+    let db = &ctx.data().database;
+    let Some(poll) = find_open_poll(ctx, &poll).await? else {
+        let msg = "There's no poll running, create one with /poll new <question>";
+        return bail_reply(ctx, msg).await;
+    };
+
+    let choices = fetch_choices(db, poll.message_id).await?;
+    let Some(icon) = ICONS
+        .iter()
+        .find(|&&icon| choices.iter().all(|c| c.icon != icon))
+    else {
+        return bail_reply(ctx, "Sorry buddy but there are enough options already.").await;
+    };
+
+    insert_choice(
+        db,
+        poll.message_id,
+        icon,
+        ctx.author().id.get() as i64,
+        &choice,
+    )
+    .await?;
+    refresh_poll_message(ctx, db, &poll).await?;
+
+    ctx.send(ephemeral_reply("Choice added.")).await?;
+
+    Ok(())
+}
+
+/// Remove a choice you added to a poll
+#[poise::command(guild_only, slash_command)]
+async fn whoops(
+    ctx: Context<'_>,
+    #[description = "The poll you added the choice to"]
+    #[autocomplete = "autocomplete_open_polls"]
+    poll: String,
+    #[description = "The choice you want to remove"] choice: String,
+) -> Result<()> {
+    let db = &ctx.data().database;
+    let Some(poll) = find_open_poll(ctx, &poll).await? else {
+        return bail_reply(ctx, "There's no poll available my guy.").await;
+    };
+
+    let choice = choice.to_lowercase();
+    let choices = fetch_choices(db, poll.message_id).await?;
+    let Some(found_choice) = choices.iter().find(|c| c.text.to_lowercase() == choice) else {
+        return bail_reply(ctx, "I couldn't find the choice.").await;
+    };
+
+    if found_choice.owner_id != ctx.author().id.get() as i64 {
+        return bail_reply(ctx, "That wasn't a choice you submitted.").await;
+    }
+
+    sqlx::query!("DELETE FROM PollChoice WHERE id = ?", found_choice.id)
+        .execute(db)
+        .await?;
+    refresh_poll_message(ctx, db, &poll).await?;
+
+    ctx.send(ephemeral_reply("That choice has been removed."))
+        .await?;
+
+    Ok(())
+}
+
+async fn find_open_poll(ctx: Context<'_>, poll: &str) -> Result<Option<PollRecord>> {
+    let Ok(message_id) = poll.parse::<i64>() else {
+        return Ok(None);
+    };
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /poll to be guild only.")
+        .get() as i64;
+
+    let row = sqlx::query_as!(
+        PollRecord,
+        r#"SELECT message_id, channel_id, question, closes_at, closed, reminder_role_id,
+            kind AS "kind: PollKind", max_picks
+        FROM Poll WHERE message_id = ? AND guild_id = ? AND NOT closed"#,
+        message_id,
+        guild_id
+    )
+    .fetch_optional(&ctx.data().database)
+    .await?;
+
+    Ok(row)
+}
+
+pub async fn fetch_poll(
+    executor: impl SqliteExecutor<'_>,
+    message_id: i64,
+) -> Result<Option<PollRecord>> {
+    let row = sqlx::query_as!(
+        PollRecord,
+        r#"SELECT message_id, channel_id, question, closes_at, closed, reminder_role_id,
+            kind AS "kind: PollKind", max_picks
+        FROM Poll WHERE message_id = ?"#,
+        message_id
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(row)
+}
+
+/// Every ranked ballot of a poll, as the choice ids from most to least favourite.
+pub async fn fetch_ballots(
+    executor: impl SqliteExecutor<'_>,
+    poll_id: i64,
+) -> Result<Vec<Vec<i64>>> {
+    let rows = sqlx::query!(
+        "SELECT user_id, choice_id FROM PollVote WHERE poll_id = ? ORDER BY user_id, rank",
+        poll_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    let mut ballots: Vec<(i64, Vec<i64>)> = Vec::new();
+    for row in rows {
+        match ballots.last_mut() {
+            Some((user_id, ballot)) if *user_id == row.user_id => ballot.push(row.choice_id),
+            _ => ballots.push((row.user_id, vec![row.choice_id])),
+        }
+    }
+
+    Ok(ballots.into_iter().map(|(_, ballot)| ballot).collect())
+}
+
+pub async fn fetch_voter_count(executor: impl SqliteExecutor<'_>, poll_id: i64) -> Result<i64> {
+    let row = sqlx::query!(
+        r#"SELECT COUNT(DISTINCT user_id) AS "voters!: i64" FROM PollVote WHERE poll_id = ?"#,
+        poll_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(row.voters)
+}
+
+pub async fn fetch_results(db: &SqlitePool, poll: &PollRecord) -> Result<PollResults> {
+    let choices = fetch_choices(db, poll.message_id).await?;
+    let voters = fetch_voter_count(db, poll.message_id).await?;
+    let runoff = match poll.kind {
+        PollKind::Ranked => {
+            let choice_ids = choices.iter().map(|c| c.id).collect::<Vec<_>>();
+            let ballots = fetch_ballots(db, poll.message_id).await?;
+            Some(instant_runoff(&choice_ids, &ballots))
+        }
+        PollKind::Single | PollKind::Multi => None,
+    };
+
+    Ok(PollResults {
+        choices,
+        voters,
+        runoff,
+    })
+}
+
+pub async fn fetch_choices(
+    executor: impl SqliteExecutor<'_>,
+    poll_id: i64,
+) -> Result<Vec<ChoiceRecord>> {
+    let rows = sqlx::query_as!(
+        ChoiceRecord,
+        r#"SELECT c.id, c.icon, c.owner_id, c.text, COUNT(v.user_id) AS "votes!: i64"
+        FROM PollChoice c LEFT JOIN PollVote v ON v.choice_id = c.id
+        WHERE c.poll_id = ?
+        GROUP BY c.id
+        ORDER BY c.id"#,
+        poll_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows)
+}
+
+async fn insert_poll(
+    executor: impl SqliteExecutor<'_>,
+    message: &Message,
+    guild_id: i64,
+    author_id: i64,
+    question: &str,
+    settings: &PollSettings,
+) -> Result<PollRecord> {
+    let message_id = message.id.get() as i64;
+    let channel_id = message.channel_id.get() as i64;
+
+    let row = sqlx::query_as!(
+        PollRecord,
+        r#"INSERT INTO Poll (message_id, channel_id, guild_id, author_id, question,
+            closes_at, reminder_role_id, kind, max_picks)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        RETURNING message_id AS "message_id!", channel_id AS "channel_id!",
+            question AS "question!", closes_at, closed AS "closed!", reminder_role_id,
+            kind AS "kind!: PollKind", max_picks AS "max_picks!""#,
+        message_id,
+        channel_id,
+        guild_id,
+        author_id,
+        question,
+        settings.closes_at,
+        settings.reminder_role_id,
+        settings.kind,
+        settings.max_picks
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(row)
+}
+
+async fn insert_choice(
+    executor: impl SqliteExecutor<'_>,
+    poll_id: i64,
+    icon: &str,
+    owner_id: i64,
+    text: &str,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO PollChoice (poll_id, icon, owner_id, text) VALUES (?, ?, ?, ?)",
+        poll_id,
+        icon,
+        owner_id,
+        text
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Stop accepting votes and replace the ballot with the final results.
+pub async fn close_poll(
+    cache_http: impl CacheHttp,
+    db: &SqlitePool,
+    poll: &PollRecord,
+) -> Result<PollResults> {
+    let results = fetch_results(db, poll).await?;
+    let message = EditMessage::new()
+        .embed(results_embed(poll, &results))
+        .components(Vec::new());
+    poll_channel(poll)
+        .edit_message(cache_http, poll_message(poll), message)
+        .await?;
+
+    // Only once the results are up, so a failed edit is retried instead of leaving the ballot up.
+    sqlx::query!(
+        "UPDATE Poll SET closed = TRUE WHERE message_id = ?",
+        poll.message_id
+    )
+    .execute(db)
+    .await?;
+
+    Ok(results)
+}
+
+/// Close a poll whose voting time ran out and post the winner in its channel.
+pub async fn close_expired_poll(
+    cache_http: impl CacheHttp,
+    db: &SqlitePool,
+    poll: &PollRecord,
+) -> Result<()> {
+    let results = close_poll(&cache_http, db, poll).await?;
+    let Some(embed) = winner_embed(poll, &results) else {
+        return Ok(());
+    };
+
+    let message = CreateMessage::new()
+        .embed(embed)
+        .reference_message((poll_channel(poll), poll_message(poll)));
+    poll_channel(poll).send_message(cache_http, message).await?;
+
+    Ok(())
+}
+
+/// Redraw the poll message after its choices or votes changed.
+pub async fn refresh_poll_message(
+    cache_http: impl CacheHttp,
+    db: &SqlitePool,
+    poll: &PollRecord,
+) -> Result<()> {
+    let choices = fetch_choices(db, poll.message_id).await?;
+    let voters = fetch_voter_count(db, poll.message_id).await?;
+    let components = match poll.kind {
+        PollKind::Single => vote_buttons(&choices),
+        PollKind::Multi => pick_menu(poll, &choices),
+        PollKind::Ranked => rank_button(poll, &choices),
+    };
+    let message = EditMessage::new()
+        .embed(poll_embed(poll, &choices, voters))
+        .components(components);
+
+    poll_channel(poll)
+        .edit_message(cache_http, poll_message(poll), message)
+        .await?;
+
+    Ok(())
+}
+
+pub fn poll_channel(poll: &PollRecord) -> ChannelId {
+    ChannelId::new(poll.channel_id as u64)
+}
+
+pub fn poll_message(poll: &PollRecord) -> MessageId {
+    MessageId::new(poll.message_id as u64)
+}
+
+fn poll_embed(poll: &PollRecord, choices: &[ChoiceRecord], voters: i64) -> CreateEmbed {
+    let mut description = choices
+        .iter()
+        .map(|c| format!("{} {}", c.icon, c.text))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if choices.is_empty() {
+        description.push_str("Add an option with /poll choice <option>");
+    }
+    match poll.kind {
+        PollKind::Single => {}
+        PollKind::Multi => description.push_str(&format!(
+            "\n\nPick up to {} choices",
+            max_picks(poll, choices)
+        )),
+        PollKind::Ranked => {
+            description.push_str("\n\nRank the choices from your favourite to least favourite")
+        }
+    }
+    if let Some(closes_at) = poll.closes_at {
+        description.push_str(&format!(
+            "\n\nVoting ends <t:{}:R>",
+            closes_at.and_utc().timestamp()
+        ));
+    }
+
+    CreateEmbed::default()
+        .title(&poll.question)
+        .description(description)
+        .footer(CreateEmbedFooter::new(format!("{voters} votes so far")))
+}
+
+fn results_embed(poll: &PollRecord, results: &PollResults) -> CreateEmbed {
+    let voters = results.voters;
+    let embed = CreateEmbed::default()
+        .title(fit_title(format!("{} results", poll.question)))
+        .footer(CreateEmbedFooter::new(format!(
+            "The poll is closed. {voters} votes in total."
+        )));
+
+    if let Some(runoff) = &results.runoff {
+        return runoff_fields(embed, &results.choices, runoff);
+    }
+
+    let lines = results
+        .choices
+        .iter()
+        .map(|c| {
+            format!(
+                "{} **{}**\n`{}` {}% ({} votes)",
+                c.icon,
+                c.text,
+                result_bar(c.votes, voters),
+                percentage(c.votes, voters),
+                c.votes
+            )
+        })
+        .collect::<Vec<_>>();
+
+    embed.description(truncate_lines(&lines, MAX_DESCRIPTION_LENGTH))
+}
+
+/// Show who dropped out in every round of a ranked poll, and the votes in the final round.
+fn runoff_fields(embed: CreateEmbed, choices: &[ChoiceRecord], runoff: &Runoff) -> CreateEmbed {
+    let mut lines = vec!["Counted with instant runoff".to_string()];
+    lines.extend(elimination_lines(choices, runoff));
+    let embed = embed.description(truncate_lines(&lines, MAX_DESCRIPTION_LENGTH));
+
+    let Some(last) = runoff.rounds.last() else {
+        return embed;
+    };
+
+    let counted: usize = last.tallies.iter().map(|(_, votes)| votes).sum();
+    let tallies = last
+        .tallies
+        .iter()
+        .map(|&(id, votes)| {
+            format!(
+                "{}\n`{}` {votes} votes",
+                choice_label(choices, id),
+                result_bar(votes as i64, counted as i64)
+            )
+        })
+        .collect::<Vec<_>>();
+
+    embed.field(
+        format!("Round {}", runoff.rounds.len()),
+        truncate_lines(&tallies, MAX_FIELD_LENGTH),
+        false,
+    )
+}
+
+/// One line for every round that knocked choices out of the running.
+fn elimination_lines(choices: &[ChoiceRecord], runoff: &Runoff) -> Vec<String> {
+    runoff
+        .rounds
+        .iter()
+        .enumerate()
+        .filter(|(_, round)| !round.eliminated.is_empty())
+        .map(|(i, round)| {
+            let eliminated = round
+                .eliminated
+                .iter()
+                .map(|&id| choice_label(choices, id))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("Round {}: {eliminated} eliminated", i + 1)
+        })
+        .collect()
+}
+
+/// Cut off titles that mention a lot of choices.
+fn fit_title(title: String) -> String {
+    if title.chars().count() <= MAX_TITLE_LENGTH {
+        return title;
+    }
+
+    let mut title = title.chars().take(MAX_TITLE_LENGTH - 1).collect::<String>();
+    title.push('…');
+    title
+}
+
+fn choice_label(choices: &[ChoiceRecord], choice_id: i64) -> String {
+    choices
+        .iter()
+        .find(|c| c.id == choice_id)
+        .map(|c| format!("{} {}", c.icon, c.text))
+        .unwrap_or_else(|| "a removed choice".to_string())
+}
+
+fn max_picks(poll: &PollRecord, choices: &[ChoiceRecord]) -> usize {
+    (poll.max_picks as usize).min(choices.len())
+}
+
+fn vote_buttons(choices: &[ChoiceRecord]) -> Vec<CreateActionRow> {
+    choices
+        .chunks(BUTTONS_PER_ROW)
+        .map(|row| {
+            let buttons = row
+                .iter()
+                .map(|c| {
+                    CreateButton::new(format!("{VOTE_BUTTON}:{}", c.id))
+                        .emoji(ReactionType::Unicode(c.icon.clone()))
+                        .label(&c.text)
+                        .style(ButtonStyle::Secondary)
+                })
+                .collect();
+            CreateActionRow::Buttons(buttons)
+        })
+        .collect()
+}
+
+fn pick_menu(poll: &PollRecord, choices: &[ChoiceRecord]) -> Vec<CreateActionRow> {
+    if choices.is_empty() {
+        return Vec::new();
+    }
+
+    let max_picks = max_picks(poll, choices);
+    let menu = CreateSelectMenu::new(
+        format!("{PICK_MENU}:{}", poll.message_id),
+        CreateSelectMenuKind::String {
+            options: choice_options(choices),
+        },
+    )
+    .placeholder(format!("Pick up to {max_picks} choices"))
+    .min_values(0)
+    .max_values(max_picks as u8);
+
+    vec![CreateActionRow::SelectMenu(menu)]
+}
+
+fn rank_button(poll: &PollRecord, choices: &[ChoiceRecord]) -> Vec<CreateActionRow> {
+    if choices.is_empty() {
+        return Vec::new();
+    }
+
+    let button = CreateButton::new(format!("{RANK_BUTTON}:{}", poll.message_id))
+        .emoji('🗳')
+        .label("Rank the choices")
+        .style(ButtonStyle::Primary);
+
+    vec![CreateActionRow::Buttons(vec![button])]
+}
+
+/// The menu a voter uses to pick the choice they rank at `rank`.
+pub fn rank_menu(poll: &PollRecord, choices: &[ChoiceRecord], rank: i64) -> CreateActionRow {
+    let menu = CreateSelectMenu::new(
+        format!("{RANK_MENU}:{}:{rank}", poll.message_id),
+        CreateSelectMenuKind::String {
+            options: choice_options(choices),
+        },
+    )
+    .placeholder(format!("Pick your #{rank} choice"));
+
+    CreateActionRow::SelectMenu(menu)
+}
+
+fn choice_options(choices: &[ChoiceRecord]) -> Vec<CreateSelectMenuOption> {
+    choices
+        .iter()
+        .map(|c| {
+            CreateSelectMenuOption::new(&c.text, c.id.to_string())
+                .emoji(ReactionType::Unicode(c.icon.clone()))
+        })
+        .collect()
+}
+
+fn percentage(votes: i64, total_votes: i64) -> i64 {
+    if total_votes == 0 {
+        return 0;
+    }
+
+    (votes as f64 / total_votes as f64 * 100.0).round() as i64
+}
+
+fn result_bar(votes: i64, total_votes: i64) -> String {
+    let filled = if total_votes == 0 {
+        0
+    } else {
+        (votes as f64 / total_votes as f64 * BAR_LENGTH as f64).round() as usize
+    };
+
+    format!("{}{}", "█".repeat(filled), "░".repeat(BAR_LENGTH - filled))
+}
+
+async fn autocomplete_open_polls<'a>(
+    ctx: Context<'a>,
+    partial: &'a str,
+) -> impl Iterator<Item = AutocompleteChoice> + 'a {
+    let guild_id = ctx.guild_id().map(|g| g.get() as i64).unwrap_or_default();
+    let partial = format!("%{partial}%");
+
+    let suggestions = sqlx::query!(
+        r#"SELECT message_id AS "message_id!", question AS "question!" FROM Poll
+        WHERE guild_id = ? AND NOT closed AND question LIKE ?
+        ORDER BY message_id DESC LIMIT 25"#,
+        guild_id,
+        partial
+    )
+    .fetch_all(&ctx.data().database)
+    .await
+    .unwrap_or_else(|e| {
+        eprintln!("Error while trying to suggest autocomplete for '{partial}': {e}");
+        vec![]
+    });
+
+    suggestions
+        .into_iter()
+        .map(|r| AutocompleteChoice::new(r.question, r.message_id.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::poll::runoff::Round;
+
+    #[test]
+    fn empty_poll_has_an_empty_bar() {
+        assert_eq!(result_bar(0, 0), "░".repeat(BAR_LENGTH));
+        assert_eq!(percentage(0, 0), 0);
+    }
+
+    #[test]
+    fn bar_is_proportional_to_votes() {
+        let bar = result_bar(1, 4);
+        assert_eq!(bar.chars().filter(|&c| c == '█').count(), BAR_LENGTH / 4);
+        assert_eq!(bar.chars().count(), BAR_LENGTH);
+        assert_eq!(percentage(1, 4), 25);
+    }
+
+    #[test]
+    fn unanimous_choice_fills_the_bar() {
+        assert_eq!(result_bar(7, 7), "█".repeat(BAR_LENGTH));
+        assert_eq!(percentage(7, 7), 100);
+    }
+
+    #[test]
+    fn long_titles_are_cut_off() {
+        assert_eq!(fit_title("short".to_string()), "short");
+
+        let title = fit_title("a".repeat(300));
+        assert_eq!(title.chars().count(), MAX_TITLE_LENGTH);
+        assert!(title.ends_with('…'));
+    }
+
+    #[test]
+    fn eliminations_take_one_line_per_round() {
+        let choices = (1..=3)
+            .map(|id| ChoiceRecord {
+                id,
+                icon: ICONS[id as usize].to_string(),
+                owner_id: 0,
+                text: format!("choice {id}"),
+                votes: 0,
+            })
+            .collect::<Vec<_>>();
+        let runoff = Runoff {
+            rounds: vec![
+                Round {
+                    tallies: vec![(1, 3), (2, 2), (3, 1)],
+                    eliminated: vec![3],
+                },
+                Round {
+                    tallies: vec![(1, 4), (2, 2)],
+                    eliminated: vec![],
+                },
+            ],
+            winners: vec![1],
+        };
+
+        assert_eq!(
+            elimination_lines(&choices, &runoff),
+            vec![format!("Round 1: {} choice 3 eliminated", ICONS[3])]
+        );
+    }
+}
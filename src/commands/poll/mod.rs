@@ -0,0 +1,8 @@
+mod collector;
+mod commands;
+mod runoff;
+mod scheduler;
+
+pub use collector::*;
+pub use commands::*;
+pub use scheduler::*;
@@ -0,0 +1,119 @@
+use std::cmp::Reverse;
+
+pub struct Round {
+    /// Votes for every choice still in the running, most votes first.
+    pub tallies: Vec<(i64, usize)>,
+    pub eliminated: Vec<i64>,
+}
+
+pub struct Runoff {
+    pub rounds: Vec<Round>,
+    /// More than one winner means the last choices standing were tied.
+    pub winners: Vec<i64>,
+}
+
+/// Count ranked ballots with instant-runoff voting. Every round each ballot goes to its
+/// highest ranked choice that's still in the running, until one choice has a majority.
+pub fn instant_runoff(choice_ids: &[i64], ballots: &[Vec<i64>]) -> Runoff {
+    let mut remaining = choice_ids.to_vec();
+    let mut rounds = Vec::new();
+
+    if ballots.is_empty() || remaining.is_empty() {
+        return Runoff {
+            rounds,
+            winners: Vec::new(),
+        };
+    }
+
+    loop {
+        let mut tallies = remaining.iter().map(|&id| (id, 0)).collect::<Vec<_>>();
+        for ballot in ballots {
+            let top = ballot.iter().find(|id| remaining.contains(id));
+            if let Some(tally) = tallies.iter_mut().find(|(id, _)| Some(id) == top) {
+                tally.1 += 1;
+            }
+        }
+        // Stable, so tied choices keep the order they were added in
+        tallies.sort_by_key(|&(_, votes)| Reverse(votes));
+
+        let counted: usize = tallies.iter().map(|(_, votes)| votes).sum();
+        let (leader, most) = tallies[0];
+        if most * 2 > counted {
+            rounds.push(Round {
+                tallies,
+                eliminated: Vec::new(),
+            });
+            return Runoff {
+                rounds,
+                winners: vec![leader],
+            };
+        }
+
+        let fewest = tallies[tallies.len() - 1].1;
+        let eliminated = tallies
+            .iter()
+            .filter(|(_, votes)| *votes == fewest)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+
+        if eliminated.len() == tallies.len() {
+            rounds.push(Round {
+                tallies,
+                eliminated: Vec::new(),
+            });
+            return Runoff {
+                rounds,
+                winners: eliminated,
+            };
+        }
+
+        remaining.retain(|id| !eliminated.contains(id));
+        rounds.push(Round {
+            tallies,
+            eliminated,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn majority_wins_in_the_first_round() {
+        let ballots = vec![vec![1, 2], vec![1], vec![2, 1]];
+        let runoff = instant_runoff(&[1, 2, 3], &ballots);
+
+        assert_eq!(runoff.winners, vec![1]);
+        assert_eq!(runoff.rounds.len(), 1);
+        assert_eq!(runoff.rounds[0].tallies, vec![(1, 2), (2, 1), (3, 0)]);
+    }
+
+    #[test]
+    fn eliminated_votes_go_to_the_next_choice() {
+        let ballots = vec![vec![1], vec![1], vec![2], vec![2], vec![3, 2]];
+        let runoff = instant_runoff(&[1, 2, 3], &ballots);
+
+        assert_eq!(runoff.winners, vec![2]);
+        assert_eq!(runoff.rounds.len(), 2);
+        assert_eq!(runoff.rounds[0].eliminated, vec![3]);
+        assert_eq!(runoff.rounds[1].tallies, vec![(2, 3), (1, 2)]);
+    }
+
+    #[test]
+    fn exhausted_ballots_are_not_counted() {
+        let ballots = vec![vec![1], vec![1], vec![2], vec![2], vec![3]];
+        let runoff = instant_runoff(&[1, 2, 3], &ballots);
+
+        assert_eq!(runoff.rounds[0].eliminated, vec![3]);
+        assert_eq!(runoff.winners, vec![1, 2]);
+    }
+
+    #[test]
+    fn no_ballots_means_no_winner() {
+        let runoff = instant_runoff(&[1, 2], &[]);
+
+        assert!(runoff.rounds.is_empty());
+        assert!(runoff.winners.is_empty());
+    }
+}
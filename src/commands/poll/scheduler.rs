@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use poise::serenity_prelude::{self as serenity, CreateAllowedMentions, CreateMessage, RoleId};
+use sqlx::SqlitePool;
+
+use crate::Data;
+use crate::Result;
+
+use super::{
+    close_expired_poll, poll_channel, poll_message, PollKind, PollRecord, REMINDER_BEFORE_CLOSE,
+};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+pub async fn setup_poll_scheduler(ctx: &serenity::Context, user_data: &Data) -> Result<()> {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+
+    println!("Setup poll scheduler");
+
+    loop {
+        interval.tick().await;
+
+        if let Err(e) = send_poll_reminders(ctx, &user_data.database).await {
+            eprintln!("Error while sending poll reminders: {e}");
+        }
+        if let Err(e) = close_expired_polls(ctx, &user_data.database).await {
+            eprintln!("Error while closing expired polls: {e}");
+        }
+    }
+}
+
+async fn send_poll_reminders(ctx: &serenity::Context, db: &SqlitePool) -> Result<()> {
+    let remind_before = Utc::now().naive_utc() + REMINDER_BEFORE_CLOSE;
+    let polls = sqlx::query_as!(
+        PollRecord,
+        r#"SELECT message_id, channel_id, question, closes_at, closed, reminder_role_id,
+            kind AS "kind: PollKind", max_picks
+        FROM Poll
+        WHERE NOT closed AND NOT reminded AND reminder_role_id IS NOT NULL AND closes_at <= ?"#,
+        remind_before
+    )
+    .fetch_all(db)
+    .await?;
+
+    for poll in polls {
+        // Mark it first so a failing message doesn't get retried every minute
+        sqlx::query!(
+            "UPDATE Poll SET reminded = TRUE WHERE message_id = ?",
+            poll.message_id
+        )
+        .execute(db)
+        .await?;
+
+        let (Some(role_id), Some(closes_at)) = (poll.reminder_role_id, poll.closes_at) else {
+            continue;
+        };
+        let role = RoleId::new(role_id as u64);
+        let link = poll_message(&poll).link(poll_channel(&poll), None);
+
+        let message = CreateMessage::new()
+            .content(format!(
+                "<@&{role}> **{}** closes <t:{}:R>! Get your votes in: {link}",
+                poll.question,
+                closes_at.and_utc().timestamp()
+            ))
+            .allowed_mentions(CreateAllowedMentions::new().roles([role]));
+
+        if let Err(e) = poll_channel(&poll).send_message(ctx, message).await {
+            eprintln!("Could not remind about poll {}: {e}", poll.message_id);
+        }
+    }
+
+    Ok(())
+}
+
+async fn close_expired_polls(ctx: &serenity::Context, db: &SqlitePool) -> Result<()> {
+    let now = Utc::now().naive_utc();
+    let polls = sqlx::query_as!(
+        PollRecord,
+        r#"SELECT message_id, channel_id, question, closes_at, closed, reminder_role_id,
+            kind AS "kind: PollKind", max_picks
+        FROM Poll WHERE NOT closed AND closes_at <= ?"#,
+        now
+    )
+    .fetch_all(db)
+    .await?;
+
+    for poll in polls {
+        if let Err(e) = close_expired_poll(ctx, db, &poll).await {
+            eprintln!("Could not close poll {}: {e}", poll.message_id);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,269 @@
+use poise::futures_util::StreamExt;
+use poise::serenity_prelude::{
+    self as serenity, ButtonStyle, ComponentInteraction, ComponentInteractionCollector,
+    ComponentInteractionDataKind, CreateActionRow, CreateButton, CreateEmbed,
+    CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, CreateSelectMenu,
+    CreateSelectMenuKind, CreateSelectMenuOption, EditInteractionResponse, GuildId, Member, Role,
+    RoleId,
+};
+
+use sqlx::SqlitePool;
+
+use super::rolesub::prepare_join;
+use crate::common::{bail_reply, ephemeral_reply};
+use crate::{Context, Data, Result};
+
+const PANEL_BUTTON: &str = "role-panel";
+const PANEL_MENU: &str = "role-panel-menu";
+/// Discord allows 5 action rows with 5 buttons each, bigger panels use select menus
+const MAX_BUTTONS: usize = 25;
+const MAX_MENU_OPTIONS: usize = 25;
+const MAX_MENUS: usize = 5;
+
+/// The kind of self-assignable roles a panel lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanelKind {
+    /// `[BOT]` roles anyone can join, see `/rolesub`
+    Roles,
+    /// `[ICON]` roles where only one can be worn at a time, see `/iconsub`
+    Icons,
+}
+
+impl PanelKind {
+    fn suffix(self) -> &'static str {
+        match self {
+            PanelKind::Roles => super::rolesub::ROLE_SUFFIX,
+            PanelKind::Icons => super::icon::ROLE_SUFFIX,
+        }
+    }
+
+    fn id(self) -> &'static str {
+        match self {
+            PanelKind::Roles => "bot",
+            PanelKind::Icons => "icon",
+        }
+    }
+
+    fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "bot" => Some(PanelKind::Roles),
+            "icon" => Some(PanelKind::Icons),
+            _ => None,
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            PanelKind::Roles => "Roles",
+            PanelKind::Icons => "Icons",
+        }
+    }
+}
+
+/// Post a message in the channel with a button for every role of the kind, that keeps
+/// working after restarts.
+pub async fn post_role_panel(ctx: Context<'_>, kind: PanelKind) -> Result<()> {
+    let guild_id = ctx.guild_id().expect("Role panels are guild only");
+
+    let mut roles = guild_id
+        .roles(ctx)
+        .await?
+        .into_values()
+        .filter(|r| r.name.ends_with(kind.suffix()))
+        .collect::<Vec<_>>();
+    roles.sort_by(|a, b| a.name.cmp(&b.name));
+
+    if roles.is_empty() {
+        return bail_reply(ctx, format!("There are no `{}` roles yet.", kind.suffix())).await;
+    }
+    if roles.len() > MAX_MENU_OPTIONS * MAX_MENUS {
+        return bail_reply(ctx, "There are too many roles to fit in a panel.").await;
+    }
+
+    let description = match kind {
+        PanelKind::Roles => "Click a role to join or leave it.",
+        PanelKind::Icons => "Click an icon to wear it, you can only wear one at a time.",
+    };
+    let embed = CreateEmbed::default()
+        .colour(0x5865F2)
+        .title(kind.title())
+        .description(description);
+
+    let panel = CreateMessage::new()
+        .embed(embed)
+        .components(panel_components(kind, &roles));
+    ctx.channel_id().send_message(ctx, panel).await?;
+
+    ctx.send(ephemeral_reply("The panel has been posted."))
+        .await?;
+
+    Ok(())
+}
+
+fn panel_components(kind: PanelKind, roles: &[Role]) -> Vec<CreateActionRow> {
+    if roles.len() <= MAX_BUTTONS {
+        return roles
+            .chunks(5)
+            .map(|chunk| {
+                let buttons = chunk
+                    .iter()
+                    .map(|r| {
+                        CreateButton::new(format!("{PANEL_BUTTON}:{}:{}", kind.id(), r.id))
+                            .label(display_name(kind, r))
+                            .style(ButtonStyle::Secondary)
+                    })
+                    .collect();
+                CreateActionRow::Buttons(buttons)
+            })
+            .collect();
+    }
+
+    roles
+        .chunks(MAX_MENU_OPTIONS)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let options = chunk
+                .iter()
+                .map(|r| CreateSelectMenuOption::new(display_name(kind, r), r.id.to_string()))
+                .collect();
+            let menu = CreateSelectMenu::new(
+                format!("{PANEL_MENU}:{}:{i}", kind.id()),
+                CreateSelectMenuKind::String { options },
+            )
+            .placeholder(format!("{} {}", kind.title(), i + 1))
+            .min_values(0)
+            .max_values(1);
+            CreateActionRow::SelectMenu(menu)
+        })
+        .collect()
+}
+
+fn display_name(kind: PanelKind, role: &Role) -> String {
+    role.name
+        .trim_end_matches(kind.suffix())
+        .trim_end()
+        .to_string()
+}
+
+pub async fn setup_role_panel_collector(ctx: &serenity::Context, user_data: &Data) -> Result<()> {
+    let mut collector = ComponentInteractionCollector::new(ctx)
+        .filter(|f| {
+            let prefix = f.data.custom_id.split(':').next();
+            [PANEL_BUTTON, PANEL_MENU]
+                .iter()
+                .any(|&p| prefix == Some(p))
+        })
+        .stream();
+
+    println!("Setup role panel collector");
+
+    while let Some(interaction) = collector.next().await {
+        if let Err(e) = handle_panel_click(ctx, &user_data.database, &interaction).await {
+            eprintln!("Error while handling role panel: {e}");
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_panel_click(
+    ctx: &serenity::Context,
+    db: &SqlitePool,
+    interaction: &ComponentInteraction,
+) -> Result<()> {
+    let mut parts = interaction.data.custom_id.split(':');
+    let prefix = parts.next();
+    let kind = parts.next().and_then(PanelKind::from_id);
+
+    let role_id = match (prefix, &interaction.data.kind) {
+        (Some(PANEL_BUTTON), _) => parts.next().and_then(|id| id.parse::<RoleId>().ok()),
+        (Some(PANEL_MENU), ComponentInteractionDataKind::StringSelect { values }) => {
+            values.first().and_then(|id| id.parse::<RoleId>().ok())
+        }
+        _ => None,
+    };
+
+    let (Some(kind), Some(role_id), Some(guild_id), Some(member)) = (
+        kind,
+        role_id,
+        interaction.guild_id,
+        interaction.member.as_ref(),
+    ) else {
+        // Clearing the select menu doesn't pick a role
+        interaction
+            .create_response(ctx, CreateInteractionResponse::Acknowledge)
+            .await?;
+        return Ok(());
+    };
+
+    // Changing roles can take longer than Discord waits for a response
+    let defer =
+        CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new().ephemeral(true));
+    interaction.create_response(ctx, defer).await?;
+
+    let message = toggle_panel_role(ctx, db, kind, guild_id, member, role_id)
+        .await
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to change the roles of {}: {e:?}", member.user.id);
+            "Failed to change your roles :(".to_string()
+        });
+    interaction
+        .edit_response(ctx, EditInteractionResponse::new().content(message))
+        .await?;
+
+    Ok(())
+}
+
+/// Join or leave the role, the message says what happened.
+async fn toggle_panel_role(
+    ctx: &serenity::Context,
+    db: &SqlitePool,
+    kind: PanelKind,
+    guild_id: GuildId,
+    member: &Member,
+    role_id: RoleId,
+) -> Result<String> {
+    let roles = guild_id.roles(ctx).await?;
+    let Some(role) = roles
+        .get(&role_id)
+        .filter(|r| r.name.ends_with(kind.suffix()))
+    else {
+        return Ok("This role does not exist anymore.".to_string());
+    };
+
+    let user_id = member.user.id;
+    if member.roles.contains(&role_id) {
+        ctx.http
+            .remove_member_role(guild_id, user_id, role_id, Some("Role panel"))
+            .await?;
+        return Ok(format!("The `{}` role has been removed!", role.name));
+    }
+
+    let conflicts = match kind {
+        PanelKind::Roles => match prepare_join(ctx, db, guild_id, member, role_id).await {
+            Ok(conflicts) => conflicts,
+            Err(reason) => return Ok(reason.to_string()),
+        },
+        // Only one icon can be worn at a time
+        PanelKind::Icons => member
+            .roles
+            .iter()
+            .filter(|r| {
+                roles
+                    .get(r)
+                    .is_some_and(|r| r.name.ends_with(kind.suffix()))
+            })
+            .copied()
+            .collect(),
+    };
+    for conflict in conflicts {
+        ctx.http
+            .remove_member_role(guild_id, user_id, conflict, Some("Role panel"))
+            .await?;
+    }
+
+    ctx.http
+        .add_member_role(guild_id, user_id, role_id, Some("Role panel"))
+        .await?;
+    Ok(format!("The `{}` role has been added!", role.name))
+}
@@ -1,13 +1,40 @@
-use serenity::all::{EditRole, GuildId, RoleId};
+use std::collections::{BTreeMap, HashMap};
 
-use crate::{common::bail_reply, Context, Result};
+use anyhow::bail;
+use poise::CreateReply;
+use serenity::all::{Cache, CacheHttp, CreateEmbed, EditRole, GuildId, Member, RoleId};
+use sqlx::SqlitePool;
 
-const ROLE_SUFFIX: &str = "[BOT]";
+use super::colors::to_color;
+use super::role_panel::{post_role_panel, PanelKind};
+use crate::{
+    common::{
+        autocomplete_suffixed_roles, bail_reply, ephemeral_reply, none_to_clear, truncate_lines,
+    },
+    Context, Result,
+};
+
+pub const ROLE_SUFFIX: &str = "[BOT]";
+const DEFAULT_CATEGORY: &str = "Other";
+/// The most members Discord sends at once.
+const MEMBERS_PER_PAGE: u64 = 1000;
+
+/// Extra information about a bot role, roles without it have no limits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfRole {
+    pub role_id: RoleId,
+    pub description: Option<String>,
+    pub category: Option<String>,
+    pub colour: Option<i64>,
+    /// Joining a role removes the other roles of the same group
+    pub exclusive_group: Option<String>,
+    pub max_members: Option<i64>,
+}
 
 #[poise::command(
     guild_only,
     slash_command,
-    subcommands("create", "delete"),
+    subcommands("create", "edit", "delete", "panel"),
     required_permissions = "ADMINISTRATOR",
     default_member_permissions = "ADMINISTRATOR"
 )]
@@ -16,10 +43,20 @@ pub async fn role(_ctx: Context<'_>) -> Result<()> {
 }
 
 /// MOD ONLY: Create bot role for this server
-#[poise::command(guild_only, slash_command)]
+#[poise::command(guild_only, slash_command, required_permissions = "ADMINISTRATOR")]
 async fn create(
     ctx: Context<'_>,
     #[description = "Name of the role to create"] role: String,
+    #[description = "What the role list says about the role"]
+    #[max_length = 100]
+    description: Option<String>,
+    #[description = "The category the role is listed under"] category: Option<String>,
+    #[description = "The 6 digit hex colour of the role"] colour: Option<String>,
+    #[description = "Joining the role removes the other roles of this group"]
+    exclusive_group: Option<String>,
+    #[description = "How many members the role can have at most"]
+    #[min = 1]
+    max_members: Option<u32>,
 ) -> Result<()> {
     let guild_id = ctx.guild_id().expect("/role create was not run in a guild");
 
@@ -28,13 +65,33 @@ async fn create(
         return bail_reply(ctx, format!("`{role_name}` already exists.")).await;
     }
 
-    if let Err(e) = guild_id
-        .create_role(ctx, EditRole::default().name(&role_name).mentionable(true))
-        .await
-    {
-        eprintln!("Failed to create {role_name}: {e:?}");
-        return bail_reply(ctx, format!("Failed to create `{role_name}` role.")).await;
+    let colour = match colour.as_deref().map(to_color) {
+        Some(None) => return bail_reply(ctx, "Please provide a valid hex color code.").await,
+        Some(Some(colour)) => Some(colour),
+        None => None,
+    };
+
+    let mut new_role = EditRole::default().name(&role_name).mentionable(true);
+    if let Some(colour) = colour {
+        new_role = new_role.colour(colour);
     }
+    let role_id = match guild_id.create_role(ctx, new_role).await {
+        Ok(role) => role.id,
+        Err(e) => {
+            eprintln!("Failed to create {role_name}: {e:?}");
+            return bail_reply(ctx, format!("Failed to create `{role_name}` role.")).await;
+        }
+    };
+
+    let self_role = SelfRole {
+        role_id,
+        description,
+        category,
+        colour: colour.map(i64::from),
+        exclusive_group,
+        max_members: max_members.map(i64::from),
+    };
+    save_self_role(&ctx.data().database, guild_id, &self_role).await?;
 
     let success_message =
         format!("`{role_name}` was successfully created! Use `/rolesub {role}` to join it.",);
@@ -43,11 +100,77 @@ async fn create(
     Ok(())
 }
 
+/// MOD ONLY: Change what the role list says about a bot role and who can join it
+#[poise::command(guild_only, slash_command, required_permissions = "ADMINISTRATOR")]
+async fn edit(
+    ctx: Context<'_>,
+    #[description = "The name of the role to change"]
+    #[autocomplete = "autocomplete_bot_roles"]
+    role: String,
+    #[description = "What the role list says about the role, or none"]
+    #[max_length = 100]
+    description: Option<String>,
+    #[description = "The category the role is listed under, or none"] category: Option<String>,
+    #[description = "The 6 digit hex colour of the role, or none"] colour: Option<String>,
+    #[description = "Joining the role removes the other roles of this group, or none"]
+    exclusive_group: Option<String>,
+    #[description = "How many members the role can have at most, 0 for no limit"]
+    max_members: Option<u32>,
+) -> Result<()> {
+    let guild_id = ctx.guild_id().expect("/role edit was not run in a guild");
+
+    let role_name = to_role_name(&role);
+    let Some(role_id) = get_server_role(ctx, guild_id, &role_name).await else {
+        return bail_reply(ctx, format!("`{role_name}` doesn't exist.")).await;
+    };
+
+    let db = &ctx.data().database;
+    let mut self_role = fetch_self_role(db, role_id)
+        .await?
+        .unwrap_or_else(|| SelfRole::new(role_id));
+
+    if let Some(colour) = colour {
+        let new_colour = match to_color(&colour) {
+            Some(colour) => Some(colour),
+            None if colour.eq_ignore_ascii_case("none") => None,
+            None => return bail_reply(ctx, "Please provide a valid hex color code.").await,
+        };
+        guild_id
+            .edit_role(
+                ctx,
+                role_id,
+                EditRole::new().colour(new_colour.unwrap_or(0)),
+            )
+            .await?;
+        self_role.colour = new_colour.map(i64::from);
+    }
+    if let Some(description) = description {
+        self_role.description = none_to_clear(description);
+    }
+    if let Some(category) = category {
+        self_role.category = none_to_clear(category);
+    }
+    if let Some(exclusive_group) = exclusive_group {
+        self_role.exclusive_group = none_to_clear(exclusive_group);
+    }
+    if let Some(max_members) = max_members {
+        self_role.max_members = (max_members > 0).then_some(max_members.into());
+    }
+
+    save_self_role(db, guild_id, &self_role).await?;
+    ctx.send(ephemeral_reply(format!("`{role_name}` has been updated.")))
+        .await?;
+
+    Ok(())
+}
+
 /// MOD ONLY: Delete bot role from the server
-#[poise::command(guild_only, slash_command)]
+#[poise::command(guild_only, slash_command, required_permissions = "ADMINISTRATOR")]
 async fn delete(
     ctx: Context<'_>,
-    #[description = "The name of the role to be deleted"] role: String,
+    #[description = "The name of the role to be deleted"]
+    #[autocomplete = "autocomplete_bot_roles"]
+    role: String,
 ) -> Result<()> {
     let guild_id = ctx.guild_id().expect("/role delete was not run in a guild");
 
@@ -61,19 +184,89 @@ async fn delete(
         return bail_reply(ctx, format!("Failed to delete `{role_name}` role.")).await;
     }
 
+    let id = role_id.get() as i64;
+    sqlx::query!("DELETE FROM SelfRole WHERE role_id = ?", id)
+        .execute(&ctx.data().database)
+        .await?;
+
     ctx.say(format!("`{role_name}` was successfully removed!"))
         .await?;
 
     Ok(())
 }
 
+/// MOD ONLY: Post a message with a button for every bot role
+#[poise::command(guild_only, slash_command, required_permissions = "ADMINISTRATOR")]
+async fn panel(ctx: Context<'_>) -> Result<()> {
+    post_role_panel(ctx, PanelKind::Roles).await
+}
+
+/// Show the bot roles of this server and how many people joined them
+#[poise::command(guild_only, slash_command)]
+pub async fn roles(ctx: Context<'_>) -> Result<()> {
+    let guild_id = ctx.guild_id().expect("/roles was not run in a guild");
+    // Counting the members of every role can take a while in big servers
+    ctx.defer_ephemeral().await?;
+
+    let roles = guild_id.roles(ctx).await?;
+    let self_roles = fetch_self_roles(&ctx.data().database, guild_id)
+        .await?
+        .into_iter()
+        .map(|r| (r.role_id, r))
+        .collect::<HashMap<_, _>>();
+    let member_counts = role_member_counts(ctx, guild_id).await?;
+
+    let mut categories = BTreeMap::<String, Vec<String>>::new();
+    let mut bot_roles = roles
+        .values()
+        .filter_map(|r| Some((r.id, r.name.strip_suffix(ROLE_SUFFIX)?.trim_end())))
+        .collect::<Vec<_>>();
+    bot_roles.sort_by_key(|&(_, name)| name);
+
+    for (role_id, name) in bot_roles {
+        let self_role = self_roles.get(&role_id);
+        let members = member_counts.get(&role_id).copied().unwrap_or_default();
+        let category = self_role
+            .and_then(|r| r.category.clone())
+            .unwrap_or_else(|| DEFAULT_CATEGORY.to_string());
+
+        categories
+            .entry(category)
+            .or_default()
+            .push(describe_role(name, self_role, members));
+    }
+
+    if categories.is_empty() {
+        return bail_reply(ctx, "There are no bot roles yet.").await;
+    }
+
+    let fields = categories
+        .into_iter()
+        .take(25)
+        .map(|(category, lines)| (category, truncate_lines(&lines, 1024), false));
+    let embed = CreateEmbed::default()
+        .colour(0x5865F2)
+        .title("Roles")
+        .description("Join one with `/rolesub`")
+        .fields(fields);
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
 /// Add/Remove a bot role from your roles
 #[poise::command(guild_only, slash_command)]
 pub async fn rolesub(
     ctx: Context<'_>,
-    #[description = "Select the emoji for the role you want to join"] role: String,
+    #[description = "Select the role you want to join"]
+    #[autocomplete = "autocomplete_bot_roles"]
+    role: String,
 ) -> Result<()> {
     let guild_id = ctx.guild_id().expect("/rolesub was not run on a guild");
+    // Full roles need their members counted before joining
+    ctx.defer_ephemeral().await?;
     let role_name = to_role_name(&role);
 
     let Some(role_id) = get_server_role(ctx, guild_id, &role_name).await else {
@@ -94,6 +287,13 @@ pub async fn rolesub(
             bail_reply(ctx, format!("The `{role_name}` role has been removed!")).await
         }
         None => {
+            let db = &ctx.data().database;
+            let conflicts = match prepare_join(ctx, db, guild_id, &author, role_id).await {
+                Ok(conflicts) => conflicts,
+                Err(reason) => return bail_reply(ctx, reason.to_string()).await,
+            };
+            author.remove_roles(ctx, &conflicts).await?;
+
             if let Err(e) = author.add_role(ctx, role_id).await {
                 eprintln!("Failed to add the {role_name} role, {e:?}");
                 return bail_reply(ctx, "Failed to add the role :(").await;
@@ -103,6 +303,203 @@ pub async fn rolesub(
     }
 }
 
+impl SelfRole {
+    fn new(role_id: RoleId) -> Self {
+        Self {
+            role_id,
+            description: None,
+            category: None,
+            colour: None,
+            exclusive_group: None,
+            max_members: None,
+        }
+    }
+}
+
+/// Make sure someone can join a bot role, returning the roles of the same exclusive group
+/// they have to leave first.
+pub async fn prepare_join(
+    cache_http: impl CacheHttp,
+    db: &SqlitePool,
+    guild_id: GuildId,
+    member: &Member,
+    role_id: RoleId,
+) -> Result<Vec<RoleId>> {
+    let self_roles = fetch_self_roles(db, guild_id).await?;
+    let Some(self_role) = self_roles.iter().find(|r| r.role_id == role_id) else {
+        return Ok(Vec::new());
+    };
+
+    if let Some(max_members) = self_role.max_members {
+        let members = role_member_counts(cache_http, guild_id)
+            .await?
+            .get(&role_id)
+            .copied()
+            .unwrap_or_default();
+        if members as i64 >= max_members {
+            bail!("This role is full, it can have at most {max_members} members.");
+        }
+    }
+
+    Ok(exclusive_conflicts(&self_roles, role_id, &member.roles))
+}
+
+fn exclusive_conflicts(
+    self_roles: &[SelfRole],
+    role_id: RoleId,
+    member_roles: &[RoleId],
+) -> Vec<RoleId> {
+    let Some(group) = self_roles
+        .iter()
+        .find(|r| r.role_id == role_id)
+        .and_then(|r| r.exclusive_group.as_deref())
+    else {
+        return Vec::new();
+    };
+
+    self_roles
+        .iter()
+        .filter(|r| r.role_id != role_id && r.exclusive_group.as_deref() == Some(group))
+        .map(|r| r.role_id)
+        .filter(|r| member_roles.contains(r))
+        .collect()
+}
+
+fn describe_role(name: &str, self_role: Option<&SelfRole>, members: usize) -> String {
+    let members = match self_role.and_then(|r| r.max_members) {
+        Some(max_members) => format!("{members}/{max_members} members"),
+        None if members == 1 => "1 member".to_string(),
+        None => format!("{members} members"),
+    };
+
+    match self_role.and_then(|r| r.description.as_deref()) {
+        Some(description) => format!("**{name}** ({members}): {description}"),
+        None => format!("**{name}** ({members})"),
+    }
+}
+
+/// How many members have each role.
+async fn role_member_counts(
+    cache_http: impl CacheHttp,
+    guild_id: GuildId,
+) -> Result<HashMap<RoleId, usize>> {
+    if let Some(counts) = cache_http
+        .cache()
+        .and_then(|cache| cached_role_member_counts(cache, guild_id))
+    {
+        return Ok(counts);
+    }
+
+    let mut counts = HashMap::new();
+    let mut after = None;
+    loop {
+        let members = guild_id
+            .members(cache_http.http(), Some(MEMBERS_PER_PAGE), after)
+            .await?;
+        count_roles(&mut counts, &members);
+
+        match members.last() {
+            Some(last) if members.len() as u64 == MEMBERS_PER_PAGE => after = Some(last.user.id),
+            _ => return Ok(counts),
+        }
+    }
+}
+
+/// `None` when the cache doesn't have every member of the guild yet.
+fn cached_role_member_counts(cache: &Cache, guild_id: GuildId) -> Option<HashMap<RoleId, usize>> {
+    let guild = cache.guild(guild_id)?;
+    // Large guilds only send the online members until the rest are requested
+    if guild.members.len() as u64 != guild.member_count {
+        return None;
+    }
+
+    let mut counts = HashMap::new();
+    count_roles(&mut counts, guild.members.values());
+    Some(counts)
+}
+
+fn count_roles<'a>(
+    counts: &mut HashMap<RoleId, usize>,
+    members: impl IntoIterator<Item = &'a Member>,
+) {
+    for role_id in members.into_iter().flat_map(|m| &m.roles) {
+        *counts.entry(*role_id).or_default() += 1;
+    }
+}
+
+async fn fetch_self_roles(db: &SqlitePool, guild_id: GuildId) -> Result<Vec<SelfRole>> {
+    let guild_id = guild_id.get() as i64;
+    let rows = sqlx::query!(
+        r#"SELECT role_id AS "role_id!", description, category, colour, exclusive_group,
+            max_members
+        FROM SelfRole WHERE guild_id = ?"#,
+        guild_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    let roles = rows
+        .into_iter()
+        .map(|r| SelfRole {
+            role_id: RoleId::new(r.role_id as u64),
+            description: r.description,
+            category: r.category,
+            colour: r.colour,
+            exclusive_group: r.exclusive_group,
+            max_members: r.max_members,
+        })
+        .collect();
+
+    Ok(roles)
+}
+
+async fn fetch_self_role(db: &SqlitePool, role_id: RoleId) -> Result<Option<SelfRole>> {
+    let id = role_id.get() as i64;
+    let row = sqlx::query!(
+        "SELECT description, category, colour, exclusive_group, max_members
+        FROM SelfRole WHERE role_id = ?",
+        id
+    )
+    .fetch_optional(db)
+    .await?;
+
+    Ok(row.map(|r| SelfRole {
+        role_id,
+        description: r.description,
+        category: r.category,
+        colour: r.colour,
+        exclusive_group: r.exclusive_group,
+        max_members: r.max_members,
+    }))
+}
+
+async fn save_self_role(db: &SqlitePool, guild_id: GuildId, role: &SelfRole) -> Result<()> {
+    let guild_id = guild_id.get() as i64;
+    let role_id = role.role_id.get() as i64;
+    sqlx::query!(
+        "INSERT INTO SelfRole
+            (role_id, guild_id, description, category, colour, exclusive_group, max_members)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT (role_id) DO UPDATE SET
+            description = excluded.description,
+            category = excluded.category,
+            colour = excluded.colour,
+            exclusive_group = excluded.exclusive_group,
+            max_members = excluded.max_members",
+        role_id,
+        guild_id,
+        role.description,
+        role.category,
+        role.colour,
+        role.exclusive_group,
+        role.max_members
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
 fn to_role_name(role_name: &str) -> String {
     format!("{role_name} {ROLE_SUFFIX}")
 }
@@ -115,3 +512,60 @@ async fn get_server_role(ctx: Context<'_>, guild_id: GuildId, role_name: &str) -
         .find(|r| r.name == role_name)
         .map(|r| r.id)
 }
+
+async fn autocomplete_bot_roles<'a>(
+    ctx: Context<'a>,
+    partial: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+    autocomplete_suffixed_roles(ctx, partial, ROLE_SUFFIX).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn self_role(id: u64, group: Option<&str>) -> SelfRole {
+        SelfRole {
+            exclusive_group: group.map(str::to_string),
+            ..SelfRole::new(RoleId::new(id))
+        }
+    }
+
+    #[test]
+    fn joining_an_exclusive_role_leaves_the_rest_of_its_group() {
+        let roles = [
+            self_role(1, Some("team")),
+            self_role(2, Some("team")),
+            self_role(3, Some("team")),
+            self_role(4, Some("region")),
+        ];
+        let member_roles = [RoleId::new(2), RoleId::new(4)];
+
+        assert_eq!(
+            exclusive_conflicts(&roles, RoleId::new(1), &member_roles),
+            vec![RoleId::new(2)]
+        );
+        assert!(exclusive_conflicts(&roles, RoleId::new(5), &member_roles).is_empty());
+    }
+
+    #[test]
+    fn roles_without_a_group_have_no_conflicts() {
+        let roles = [self_role(1, None), self_role(2, None)];
+        let member_roles = [RoleId::new(2)];
+
+        assert!(exclusive_conflicts(&roles, RoleId::new(1), &member_roles).is_empty());
+    }
+
+    #[test]
+    fn describes_member_limits() {
+        let mut role = self_role(1, None);
+        assert_eq!(describe_role("Gamers", None, 1), "**Gamers** (1 member)");
+
+        role.max_members = Some(10);
+        role.description = Some("We game".to_string());
+        assert_eq!(
+            describe_role("Gamers", Some(&role), 3),
+            "**Gamers** (3/10 members): We game"
+        );
+    }
+}
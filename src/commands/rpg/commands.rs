@@ -7,11 +7,11 @@ use crate::common::{
     avatar_url, bail_reply, ephemeral_text_message, name, nickname, reply_with_buttons, response,
     text_message, update_response, Score,
 };
-use crate::config::{RPG_DEAD_DUEL_COOLDOWN, RPG_LOSS_COOLDOWN};
+use crate::config::GuildConfig;
 use crate::Context;
 
 use anyhow::{bail, Context as DiscordContext, Result};
-use chrono::{NaiveDateTime, Utc};
+use chrono::{NaiveDateTime, TimeDelta, Utc};
 use poise::serenity_prelude::{ButtonStyle, CreateActionRow};
 use poise::serenity_prelude::{
     CreateButton, CreateEmbed, CreateEmbedAuthor, Mention, User, UserId,
@@ -44,12 +44,16 @@ async fn challenge(
     }
 
     let challenger = ctx.author();
+    let guild_id = ctx
+        .guild_id()
+        .expect("Expected /rpg challenge to be guild only.");
+    let config = ctx.data().guild_config(guild_id).await;
 
     let Ok(challenger_stats) = retrieve_user_stats(ctx, challenger).await else {
         return bail_reply(ctx, "Something went wrong when trying to join the fight.").await;
     };
 
-    if let Err(e) = assert_no_recent_loss(&challenger_stats) {
+    if let Err(e) = assert_no_recent_loss(&challenger_stats, config.rpg_loss_cooldown) {
         return bail_reply(ctx, e.to_string()).await;
     };
 
@@ -70,7 +74,7 @@ async fn challenge(
         .await?;
 
     IN_PROGRESS.store(true, Ordering::Release);
-    if let Err(e) = run_duel(ctx, challenger_character, reply_handle, wager).await {
+    if let Err(e) = run_duel(ctx, &config, challenger_character, reply_handle, wager).await {
         eprintln!("Failed to run duel to completion: {e:?}");
     }
     IN_PROGRESS.store(false, Ordering::Release);
@@ -80,6 +84,7 @@ async fn challenge(
 
 async fn run_duel(
     ctx: Context<'_>,
+    config: &GuildConfig,
     challenger_character: Character,
     reply_handle: ReplyHandle<'_>,
     wager: String,
@@ -87,7 +92,7 @@ async fn run_duel(
     let message = reply_handle.message().await?;
 
     let Some((interaction, accepter_stats)) =
-        find_opponent(ctx, message.id, challenger_character.user_id).await?
+        find_opponent(ctx, config, message.id, challenger_character.user_id).await?
     else {
         let content = format!(
             "No one was brave enough to do battle with **{}**",
@@ -137,13 +142,14 @@ async fn run_duel(
 
 async fn find_opponent(
     ctx: Context<'_>,
+    config: &GuildConfig,
     message_id: MessageId,
     challenger_id: u64,
 ) -> Result<Option<(ComponentInteraction, CharacterPastStats)>> {
     while let Some(interaction) = ComponentInteractionCollector::new(ctx)
         .message_id(message_id)
         .filter(move |f| f.data.custom_id == "rpg-btn")
-        .timeout(RPG_DEAD_DUEL_COOLDOWN.to_std().unwrap())
+        .timeout(config.rpg_dead_duel_cooldown.to_std().unwrap())
         .await
     {
         if interaction.user.id == challenger_id {
@@ -161,7 +167,7 @@ async fn find_opponent(
         }
 
         let accepter_stats = retrieve_user_stats(ctx, &interaction.user).await?;
-        if let Err(e) = assert_no_recent_loss(&accepter_stats) {
+        if let Err(e) = assert_no_recent_loss(&accepter_stats, config.rpg_loss_cooldown) {
             interaction
                 .create_response(ctx, response(ephemeral_text_message(e.to_string())))
                 .await?;
@@ -174,9 +180,11 @@ async fn find_opponent(
     Ok(None)
 }
 
-fn assert_no_recent_loss(stats: &CharacterPastStats) -> Result<()> {
+fn assert_no_recent_loss(
+    stats: &CharacterPastStats,
+    loss_cooldown_duration: TimeDelta,
+) -> Result<()> {
     let now = Utc::now().naive_utc();
-    let loss_cooldown_duration = RPG_LOSS_COOLDOWN;
 
     if stats.last_loss + loss_cooldown_duration > now {
         let time_until_duel = (stats.last_loss + loss_cooldown_duration)
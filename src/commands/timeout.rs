@@ -1,8 +1,11 @@
-use chrono::{TimeDelta, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
+use poise::ChoiceParameter;
 use rand::Rng;
 use serenity::all::{ButtonStyle, CreateActionRow, CreateButton, CreateMessage, Member, Message};
 use sqlx::SqlitePool;
 
+use super::modlog::{try_record_action, ModerationAction, ModerationSource};
+use super::warn::pardon_warnings;
 use crate::common::{bail_reply, name, text_message, update_response};
 use crate::{Context, Result};
 
@@ -10,7 +13,9 @@ use crate::{Context, Result};
 #[poise::command(guild_only, slash_command, prefix_command)]
 pub async fn sudoku(
     ctx: Context<'_>,
-    #[description = "Your final words"] message: Option<String>,
+    #[description = "Your final words"]
+    #[max_length = 512]
+    message: Option<String>,
 ) -> Result<()> {
     let guild = ctx
         .partial_guild()
@@ -41,6 +46,18 @@ pub async fn sudoku(
         .disable_communication_until_datetime(ctx, timeout_until.into())
         .await?;
 
+    try_record_action(
+        ctx,
+        ModerationAction {
+            actor: ctx.author().id,
+            target: ctx.author().id,
+            reason: message.clone(),
+            duration: Some(TimeDelta::seconds(random_timeout)),
+            source: ModerationSource::Sudoku,
+        },
+    )
+    .await;
+
     let goodbye_message = match message {
         Some(message) => format!("\n> {message}"),
         None => String::new(),
@@ -78,6 +95,9 @@ pub async fn timeout(
     #[max = 2419200] // 28 days in seconds
     #[description = "The timeout duration in seconds"]
     duration: i64,
+    #[description = "Why they're being timed out"]
+    #[max_length = 512]
+    reason: Option<String>,
 ) -> Result<()> {
     // There should be no need to check the author's permissions inside the
     // command because if they able to see it that should already mean they are
@@ -97,12 +117,18 @@ pub async fn timeout(
     }
 
     // Timeout user
-    let until = Utc::now()
-        .checked_add_signed(TimeDelta::seconds(duration))
-        .unwrap();
-    chatter
-        .disable_communication_until_datetime(ctx, until.into())
-        .await?;
+    let until = time_out(ctx, &mut chatter, TimeDelta::seconds(duration)).await?;
+    try_record_action(
+        ctx,
+        ModerationAction {
+            actor: ctx.author().id,
+            target: chatter.user.id,
+            reason,
+            duration: Some(TimeDelta::seconds(duration)),
+            source: ModerationSource::Timeout,
+        },
+    )
+    .await;
     ctx.reply(format!(
         "{chatter} has been timed out. We'll see them again <t:{}:R>",
         until.timestamp()
@@ -120,6 +146,20 @@ pub async fn timeout(
     Ok(())
 }
 
+/// Time out a chatter for the duration, returning when they can talk again.
+pub async fn time_out(
+    ctx: Context<'_>,
+    chatter: &mut Member,
+    duration: TimeDelta,
+) -> Result<DateTime<Utc>> {
+    let until = Utc::now().checked_add_signed(duration).unwrap();
+    chatter
+        .disable_communication_until_datetime(ctx, until.into())
+        .await?;
+
+    Ok(until)
+}
+
 /// Reset a chatter's cooldown and/or remove their timeout
 #[poise::command(
     guild_only,
@@ -131,6 +171,9 @@ pub async fn pardon(
     ctx: Context<'_>,
     #[description = "The chatter you want to pardon"] mut chatter: Member,
     #[description = "The kind of action you want to reset"] kind: Option<PardonKind>,
+    #[description = "Why they're being pardoned"]
+    #[max_length = 512]
+    reason: Option<String>,
 ) -> Result<()> {
     let db = &ctx.data().database;
     let kind = kind.unwrap_or(PardonKind::All);
@@ -145,13 +188,33 @@ pub async fn pardon(
         PardonKind::DuelAndColor => {
             pardon_duel_loss(db, &mut chatter).await?;
         }
+        PardonKind::Warnings => {
+            pardon_warnings(db, &chatter).await?;
+        }
         PardonKind::All => {
             pardon_timeout(ctx, &mut chatter).await?;
             pardon_rpg_loss(db, &mut chatter).await?;
             pardon_duel_loss(db, &mut chatter).await?;
+            pardon_warnings(db, &chatter).await?;
         }
     }
 
+    let reason = match reason {
+        Some(reason) => format!("{}: {reason}", kind.name()),
+        None => kind.name().to_string(),
+    };
+    try_record_action(
+        ctx,
+        ModerationAction {
+            actor: ctx.author().id,
+            target: chatter.user.id,
+            reason: Some(reason),
+            duration: None,
+            source: ModerationSource::Pardon,
+        },
+    )
+    .await;
+
     bail_reply(ctx, "All done :)").await
 }
 
@@ -179,6 +242,17 @@ async fn listen_to_jailbreak_message(ctx: Context<'_>, handle: &Message, mut cha
             eprintln!("Failed to remove timeout for {}: {e:?}", chatter.user.name);
             continue;
         };
+        try_record_action(
+            ctx,
+            ModerationAction {
+                actor: chatter.user.id,
+                target: chatter.user.id,
+                reason: Some("Broke out of jail".to_string()),
+                duration: None,
+                source: ModerationSource::Pardon,
+            },
+        )
+        .await;
 
         if let Err(e) = interaction
             .create_response(
@@ -199,6 +273,7 @@ enum PardonKind {
     Timeout,
     Rpg,
     DuelAndColor,
+    Warnings,
     All,
 }
 
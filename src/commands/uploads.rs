@@ -0,0 +1,38 @@
+use std::path::Path;
+
+use anyhow::{bail, Context as _};
+use poise::serenity_prelude::Attachment;
+
+use crate::Result;
+
+const MAX_UPLOAD_SIZE: u32 = 8 * 1024 * 1024;
+
+/// Download a file uploaded through Discord into `folder` as `name` with the upload's
+/// extension, returning the path it's stored at.
+pub async fn save_upload(folder: &Path, name: &str, upload: &Attachment) -> Result<String> {
+    if upload.size > MAX_UPLOAD_SIZE {
+        bail!("The file can be at most 8 MB.");
+    }
+
+    let extension = Path::new(&upload.filename)
+        .extension()
+        .and_then(|e| e.to_str())
+        .filter(|e| e.chars().all(|c| c.is_ascii_alphanumeric()))
+        .unwrap_or("png");
+    let path = folder.join(format!("{name}.{extension}"));
+
+    let bytes = upload
+        .download()
+        .await
+        .context("Could not download the file, try uploading it again.")?;
+    tokio::fs::create_dir_all(folder).await?;
+    tokio::fs::write(&path, bytes).await?;
+
+    Ok(path.to_string_lossy().into_owned())
+}
+
+pub async fn remove_upload(path: &str) {
+    if let Err(e) = tokio::fs::remove_file(path).await {
+        eprintln!("Failed to remove uploaded file {path}: {e}");
+    }
+}
@@ -0,0 +1,200 @@
+use chrono::{TimeDelta, Utc};
+use poise::serenity_prelude::{GuildId, Member, UserId};
+use sqlx::SqlitePool;
+
+use super::modlog::{try_record_action, ModerationAction, ModerationSource};
+use super::timeout::time_out;
+use crate::{common::bail_reply, Context, Result};
+
+/// Give a chatter warning points, reaching a threshold times them out
+#[poise::command(
+    guild_only,
+    slash_command,
+    required_permissions = "MODERATE_MEMBERS",
+    default_member_permissions = "MODERATE_MEMBERS"
+)]
+pub async fn warn(
+    ctx: Context<'_>,
+    #[description = "The chatter you want to warn"] mut chatter: Member,
+    #[description = "How many points the warning is worth, 1 by default"]
+    #[min = 1]
+    #[max = 10]
+    points: Option<u8>,
+    #[description = "Why they're being warned"]
+    #[max_length = 512]
+    reason: Option<String>,
+) -> Result<()> {
+    let guild_id = ctx.guild_id().expect("Expected /warn to be guild only.");
+    let Some(guild) = ctx.partial_guild().await else {
+        return bail_reply(ctx, "Could not find the server to check who owns it.").await;
+    };
+    let config = ctx.data().guild_config(guild_id).await;
+    let db = &ctx.data().database;
+    let points = i64::from(points.unwrap_or(1));
+
+    let before = active_points(db, guild_id, chatter.user.id, config.warning_decay).await?;
+    insert_warning(ctx, &chatter, points, reason.as_deref()).await?;
+    let after = before + points;
+
+    let summary = format!("+{points} points, {after} in total");
+    try_record_action(
+        ctx,
+        ModerationAction {
+            actor: ctx.author().id,
+            target: chatter.user.id,
+            reason: Some(match &reason {
+                Some(reason) => format!("{reason} ({summary})"),
+                None => summary,
+            }),
+            duration: None,
+            source: ModerationSource::Warning,
+        },
+    )
+    .await;
+
+    let mut message = format!("{chatter} has been warned and now has {after} warning points.");
+
+    let duration = escalation(&config.warning_thresholds, before, after);
+    if let Some(duration) = duration.filter(|_| chatter.user.id != guild.owner_id) {
+        message = match time_out(ctx, &mut chatter, duration).await {
+            Ok(until) => {
+                try_record_action(
+                    ctx,
+                    ModerationAction {
+                        actor: ctx.author().id,
+                        target: chatter.user.id,
+                        reason: Some(format!("Reached {after} warning points")),
+                        duration: Some(duration),
+                        source: ModerationSource::Timeout,
+                    },
+                )
+                .await;
+
+                format!(
+                    "{message} That's enough to time them out, we'll see them again <t:{}:R>",
+                    until.timestamp()
+                )
+            }
+            Err(e) => {
+                eprintln!("Failed to timeout {}, reason: {e:?}", chatter.user.name);
+                format!("{message} That's enough to time them out, but the timeout failed.")
+            }
+        };
+    }
+
+    ctx.reply(message).await?;
+
+    Ok(())
+}
+
+/// Warning points that were given within the decay period and weren't pardoned.
+async fn active_points(
+    db: &SqlitePool,
+    guild_id: GuildId,
+    user_id: UserId,
+    decay: TimeDelta,
+) -> Result<i64> {
+    let guild_id = guild_id.get() as i64;
+    let user_id = user_id.get() as i64;
+    let since = (Utc::now() - decay).timestamp();
+
+    let points = sqlx::query_scalar!(
+        r#"SELECT COALESCE(SUM(points), 0) AS "points!: i64" FROM Warning
+        WHERE guild_id = ? AND user_id = ? AND NOT pardoned AND created_at > ?"#,
+        guild_id,
+        user_id,
+        since
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(points)
+}
+
+async fn insert_warning(
+    ctx: Context<'_>,
+    chatter: &Member,
+    points: i64,
+    reason: Option<&str>,
+) -> Result<()> {
+    let guild_id = chatter.guild_id.get() as i64;
+    let user_id = chatter.user.id.get() as i64;
+    let actor_id = ctx.author().id.get() as i64;
+    let created_at = Utc::now().timestamp();
+
+    sqlx::query!(
+        "INSERT INTO Warning (guild_id, user_id, actor_id, points, reason, created_at)
+        VALUES (?, ?, ?, ?, ?, ?)",
+        guild_id,
+        user_id,
+        actor_id,
+        points,
+        reason,
+        created_at
+    )
+    .execute(&ctx.data().database)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn pardon_warnings(db: &SqlitePool, chatter: &Member) -> Result<()> {
+    let guild_id = chatter.guild_id.get() as i64;
+    let user_id = chatter.user.id.get() as i64;
+
+    sqlx::query!(
+        "UPDATE Warning SET pardoned = TRUE WHERE guild_id = ? AND user_id = ? AND NOT pardoned",
+        guild_id,
+        user_id
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// The timeout for the highest threshold the warning went past, if it went past any.
+fn escalation(thresholds: &[(i64, TimeDelta)], before: i64, after: i64) -> Option<TimeDelta> {
+    thresholds
+        .iter()
+        .filter(|&&(points, _)| before < points && points <= after)
+        .map(|&(_, duration)| duration)
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn thresholds() -> Vec<(i64, TimeDelta)> {
+        vec![
+            (3, TimeDelta::minutes(10)),
+            (5, TimeDelta::hours(1)),
+            (8, TimeDelta::days(1)),
+        ]
+    }
+
+    #[test]
+    fn no_timeout_below_the_first_threshold() {
+        assert_eq!(escalation(&thresholds(), 0, 2), None);
+    }
+
+    #[test]
+    fn crossing_a_threshold_times_out() {
+        assert_eq!(
+            escalation(&thresholds(), 2, 3),
+            Some(TimeDelta::minutes(10))
+        );
+        assert_eq!(escalation(&thresholds(), 4, 6), Some(TimeDelta::hours(1)));
+    }
+
+    #[test]
+    fn thresholds_only_trigger_once() {
+        assert_eq!(escalation(&thresholds(), 3, 4), None);
+    }
+
+    #[test]
+    fn skipping_thresholds_uses_the_longest_timeout() {
+        assert_eq!(escalation(&thresholds(), 0, 10), Some(TimeDelta::days(1)));
+    }
+}
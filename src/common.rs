@@ -97,6 +97,50 @@ pub enum Score {
     Draw,
 }
 
+/// Options that can be cleared by filling in `none`.
+pub fn none_to_clear(value: String) -> Option<String> {
+    (!value.eq_ignore_ascii_case("none")).then_some(value)
+}
+
+/// Join as many lines as fit in `max_length` characters.
+pub fn truncate_lines(lines: &[String], max_length: usize) -> String {
+    let mut text = String::new();
+    for line in lines {
+        if text.chars().count() + line.chars().count() + 1 > max_length {
+            break;
+        }
+        text.push_str(line);
+        text.push('\n');
+    }
+
+    text
+}
+
+/// Names of the roles that end with the suffix, without it, for autocompleting role options.
+pub async fn autocomplete_suffixed_roles<'a>(
+    ctx: Context<'a>,
+    partial: &'a str,
+    suffix: &str,
+) -> impl Iterator<Item = String> + 'a {
+    let roles = match ctx.guild_id() {
+        Some(guild_id) => guild_id.roles(ctx).await.unwrap_or_else(|e| {
+            eprintln!("Error while trying to suggest autocomplete for '{partial}': {e}");
+            Default::default()
+        }),
+        None => Default::default(),
+    };
+
+    let partial = partial.to_lowercase();
+    let mut names = roles
+        .into_values()
+        .filter_map(|r| Some(r.name.strip_suffix(suffix)?.trim_end().to_string()))
+        .filter(|name| name.to_lowercase().contains(&partial))
+        .collect::<Vec<_>>();
+    names.sort();
+
+    names.into_iter().take(25)
+}
+
 pub fn pick_best_x_dice_rolls(
     die_sides: usize,
     total_rolls: usize,
@@ -1,5 +1,16 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail};
 use chrono::TimeDelta;
-use serenity::all::{ChannelId, GuildId, RoleId, UserId};
+use poise::ChoiceParameter;
+use serenity::all::{ChannelId, GuildId, Mentionable, RoleId, UserId};
+use serenity::utils::{parse_channel_mention, parse_role_mention};
+use sqlx::SqlitePool;
+
+use crate::{Data, Result};
+
+// NOTE: Most of the roles, channels and cooldowns below are only defaults,
+// guilds can override them with `/config`, see `GuildConfig`.
 
 pub const GUILD_ID: GuildId = GuildId::new(111135289648349184);
 
@@ -10,7 +21,6 @@ pub const GUILD_ID: GuildId = GuildId::new(111135289648349184);
 // Special users IDs
 pub const BANANA_ID: UserId = UserId::new(1234567);
 pub const GOZ_ID: UserId = UserId::new(1234567);
-pub const BLOB_ID: UserId = UserId::new(104908485266817024);
 
 // ===================
 //        Roles
@@ -55,5 +65,487 @@ pub const DRAW_TIMEOUT_DURATION: TimeDelta = TimeDelta::minutes(10);
 pub const RPS_ACCEPT_TIMEOUT: TimeDelta = TimeDelta::minutes(10);
 pub const RPS_CHOICE_TIMEOUT: TimeDelta = TimeDelta::minutes(5);
 
-// hi blob event
-pub const BLOB_HI_TIMEOUT: TimeDelta = TimeDelta::hours(10);
+// greeter
+pub const DEFAULT_GREETING_TIMEOUT: TimeDelta = TimeDelta::hours(10);
+
+// /warn
+pub const DEFAULT_WARNING_THRESHOLDS: [(i64, TimeDelta); 3] = [
+    (3, TimeDelta::minutes(10)),
+    (5, TimeDelta::hours(1)),
+    (8, TimeDelta::days(1)),
+];
+pub const WARNING_DECAY: TimeDelta = TimeDelta::days(30);
+/// Discord doesn't allow timeouts longer than this
+pub const MAX_TIMEOUT: TimeDelta = TimeDelta::days(28);
+
+// welcome
+pub const DEFAULT_WELCOME_MESSAGE: &str = "Welcome to {server}, {user.mention}!";
+
+// ===================
+//   Guild settings
+// ===================
+
+/// Per guild settings that can be changed at runtime with `/config`.
+/// Anything that hasn't been set falls back to the constants above.
+#[derive(Debug, Clone)]
+pub struct GuildConfig {
+    pub sub_role: RoleId,
+    pub color_anchor_role: RoleId,
+    pub embed_role: RoleId,
+    pub streaming_role: Option<RoleId>,
+    pub streaming_required_role: Option<RoleId>,
+    pub streaming_game: Option<String>,
+    pub streaming_announcement_channel: Option<ChannelId>,
+    pub mixu_channel: ChannelId,
+    /// Where joins and leaves are announced, nothing is posted when it's not set
+    pub welcome_channel: Option<ChannelId>,
+    pub welcome_message: String,
+    pub farewell_message: Option<String>,
+    /// Roles given to everyone that joins
+    pub starter_roles: Vec<RoleId>,
+    /// Where every moderation action is posted, nothing is posted when it's not set
+    pub mod_log_channel: Option<ChannelId>,
+    /// Reaching this many warning points times someone out for the duration
+    pub warning_thresholds: Vec<(i64, TimeDelta)>,
+    /// How long warning points count towards the thresholds
+    pub warning_decay: TimeDelta,
+
+    pub dino_gifting_cooldown: TimeDelta,
+    pub dino_slurp_cooldown: TimeDelta,
+    pub rpg_dead_duel_cooldown: TimeDelta,
+    pub rpg_loss_cooldown: TimeDelta,
+    pub gamble_fail_chance: u8,
+    pub random_color_cooldown: TimeDelta,
+    pub duel_loss_cooldown: TimeDelta,
+    pub dead_duel_cooldown: TimeDelta,
+    pub draw_timeout_duration: TimeDelta,
+}
+
+impl GuildConfig {
+    pub fn new(guild_id: GuildId) -> Self {
+        Self {
+            sub_role: SUB_ROLE,
+            color_anchor_role: COLOR_ANCHOR_ROLE,
+            embed_role: EMBED_ROLE,
+            // The streaming role is updated on every presence change so it
+            // stays opt-in for guilds other than the original one.
+            streaming_role: (guild_id == GUILD_ID).then_some(STREAMING_ROLE),
+            streaming_required_role: None,
+            streaming_game: None,
+            streaming_announcement_channel: None,
+            mixu_channel: MIXU_CHANNEL,
+            welcome_channel: None,
+            welcome_message: DEFAULT_WELCOME_MESSAGE.to_string(),
+            farewell_message: None,
+            starter_roles: Vec::new(),
+            mod_log_channel: None,
+            warning_thresholds: DEFAULT_WARNING_THRESHOLDS.to_vec(),
+            warning_decay: WARNING_DECAY,
+
+            dino_gifting_cooldown: DINO_GIFTING_COOLDOWN,
+            dino_slurp_cooldown: DINO_SLURP_COOLDOWN,
+            rpg_dead_duel_cooldown: RPG_DEAD_DUEL_COOLDOWN,
+            rpg_loss_cooldown: RPG_LOSS_COOLDOWN,
+            gamble_fail_chance: DEFAULT_GAMBLE_FAIL_CHANCE,
+            random_color_cooldown: RANDOM_COLOR_COOLDOWN,
+            duel_loss_cooldown: DUEL_LOSS_COOLDOWN,
+            dead_duel_cooldown: DEAD_DUEL_COOLDOWN,
+            draw_timeout_duration: DRAW_TIMEOUT_DURATION,
+        }
+    }
+
+    pub fn set(&mut self, key: ConfigKey, value: &str) -> Result<()> {
+        let value = value.trim();
+
+        match key {
+            ConfigKey::SubRole => self.sub_role = parse_role(value)?,
+            ConfigKey::ColorAnchorRole => self.color_anchor_role = parse_role(value)?,
+            ConfigKey::EmbedRole => self.embed_role = parse_role(value)?,
+            ConfigKey::StreamingRole => self.streaming_role = parse_optional(value, parse_role)?,
+            ConfigKey::StreamingRequiredRole => {
+                self.streaming_required_role = parse_optional(value, parse_role)?
+            }
+            ConfigKey::StreamingGame => {
+                self.streaming_game = parse_optional(value, |v| Ok(v.to_string()))?
+            }
+            ConfigKey::StreamingAnnouncementChannel => {
+                self.streaming_announcement_channel = parse_optional(value, parse_channel)?
+            }
+            ConfigKey::MixuChannel => self.mixu_channel = parse_channel(value)?,
+            ConfigKey::WelcomeChannel => {
+                self.welcome_channel = parse_optional(value, parse_channel)?
+            }
+            ConfigKey::WelcomeMessage => self.welcome_message = value.to_string(),
+            ConfigKey::FarewellMessage => {
+                self.farewell_message = parse_optional(value, |v| Ok(v.to_string()))?
+            }
+            ConfigKey::StarterRoles => {
+                self.starter_roles = parse_optional(value, parse_roles)?.unwrap_or_default()
+            }
+            ConfigKey::ModLogChannel => {
+                self.mod_log_channel = parse_optional(value, parse_channel)?
+            }
+            ConfigKey::WarningThresholds => {
+                self.warning_thresholds =
+                    parse_optional(value, parse_thresholds)?.unwrap_or_default()
+            }
+            ConfigKey::WarningDecay => self.warning_decay = parse_seconds(value)?,
+
+            ConfigKey::DinoGiftingCooldown => self.dino_gifting_cooldown = parse_seconds(value)?,
+            ConfigKey::DinoSlurpCooldown => self.dino_slurp_cooldown = parse_seconds(value)?,
+            ConfigKey::RpgDeadDuelCooldown => self.rpg_dead_duel_cooldown = parse_seconds(value)?,
+            ConfigKey::RpgLossCooldown => self.rpg_loss_cooldown = parse_seconds(value)?,
+            ConfigKey::GambleFailChance => self.gamble_fail_chance = parse_percentage(value)?,
+            ConfigKey::RandomColorCooldown => self.random_color_cooldown = parse_seconds(value)?,
+            ConfigKey::DuelLossCooldown => self.duel_loss_cooldown = parse_seconds(value)?,
+            ConfigKey::DeadDuelCooldown => self.dead_duel_cooldown = parse_seconds(value)?,
+            ConfigKey::DrawTimeoutDuration => self.draw_timeout_duration = parse_seconds(value)?,
+        }
+
+        Ok(())
+    }
+
+    /// The value of `key` formatted to be shown in a discord message.
+    pub fn get(&self, key: ConfigKey) -> String {
+        match key {
+            ConfigKey::SubRole => self.sub_role.mention().to_string(),
+            ConfigKey::ColorAnchorRole => self.color_anchor_role.mention().to_string(),
+            ConfigKey::EmbedRole => self.embed_role.mention().to_string(),
+            ConfigKey::StreamingRole => display_optional(self.streaming_role),
+            ConfigKey::StreamingRequiredRole => display_optional(self.streaming_required_role),
+            ConfigKey::StreamingGame => match &self.streaming_game {
+                Some(game) => format!("`{game}`"),
+                None => "Any".to_string(),
+            },
+            ConfigKey::StreamingAnnouncementChannel => {
+                display_optional(self.streaming_announcement_channel)
+            }
+            ConfigKey::MixuChannel => self.mixu_channel.mention().to_string(),
+            ConfigKey::WelcomeChannel => display_optional(self.welcome_channel),
+            ConfigKey::WelcomeMessage => format!("`{}`", self.welcome_message),
+            ConfigKey::FarewellMessage => match &self.farewell_message {
+                Some(message) => format!("`{message}`"),
+                None => "Disabled".to_string(),
+            },
+            ConfigKey::StarterRoles => match self.starter_roles.as_slice() {
+                [] => "None".to_string(),
+                roles => roles
+                    .iter()
+                    .map(|r| r.mention().to_string())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            },
+            ConfigKey::ModLogChannel => display_optional(self.mod_log_channel),
+            ConfigKey::WarningThresholds => match self.warning_thresholds.as_slice() {
+                [] => "None".to_string(),
+                thresholds => thresholds
+                    .iter()
+                    .map(|(points, duration)| format!("{points}:{}", duration.num_seconds()))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            },
+            ConfigKey::WarningDecay => display_seconds(self.warning_decay),
+
+            ConfigKey::DinoGiftingCooldown => display_seconds(self.dino_gifting_cooldown),
+            ConfigKey::DinoSlurpCooldown => display_seconds(self.dino_slurp_cooldown),
+            ConfigKey::RpgDeadDuelCooldown => display_seconds(self.rpg_dead_duel_cooldown),
+            ConfigKey::RpgLossCooldown => display_seconds(self.rpg_loss_cooldown),
+            ConfigKey::GambleFailChance => format!("{}%", self.gamble_fail_chance),
+            ConfigKey::RandomColorCooldown => display_seconds(self.random_color_cooldown),
+            ConfigKey::DuelLossCooldown => display_seconds(self.duel_loss_cooldown),
+            ConfigKey::DeadDuelCooldown => display_seconds(self.dead_duel_cooldown),
+            ConfigKey::DrawTimeoutDuration => display_seconds(self.draw_timeout_duration),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum ConfigKey {
+    #[name = "sub_role"]
+    SubRole,
+    #[name = "color_anchor_role"]
+    ColorAnchorRole,
+    #[name = "embed_role"]
+    EmbedRole,
+    #[name = "streaming_role"]
+    StreamingRole,
+    #[name = "streaming_required_role"]
+    StreamingRequiredRole,
+    #[name = "streaming_game"]
+    StreamingGame,
+    #[name = "streaming_announcement_channel"]
+    StreamingAnnouncementChannel,
+    #[name = "mixu_channel"]
+    MixuChannel,
+    #[name = "welcome_channel"]
+    WelcomeChannel,
+    #[name = "welcome_message"]
+    WelcomeMessage,
+    #[name = "farewell_message"]
+    FarewellMessage,
+    #[name = "starter_roles"]
+    StarterRoles,
+    #[name = "mod_log_channel"]
+    ModLogChannel,
+    #[name = "warning_thresholds"]
+    WarningThresholds,
+    #[name = "warning_decay"]
+    WarningDecay,
+    #[name = "dino_gifting_cooldown"]
+    DinoGiftingCooldown,
+    #[name = "dino_slurp_cooldown"]
+    DinoSlurpCooldown,
+    #[name = "rpg_dead_duel_cooldown"]
+    RpgDeadDuelCooldown,
+    #[name = "rpg_loss_cooldown"]
+    RpgLossCooldown,
+    #[name = "gamble_fail_chance"]
+    GambleFailChance,
+    #[name = "random_color_cooldown"]
+    RandomColorCooldown,
+    #[name = "duel_loss_cooldown"]
+    DuelLossCooldown,
+    #[name = "dead_duel_cooldown"]
+    DeadDuelCooldown,
+    #[name = "draw_timeout_duration"]
+    DrawTimeoutDuration,
+}
+
+impl ConfigKey {
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..).map_while(Self::from_index)
+    }
+}
+
+fn parse_role(value: &str) -> Result<RoleId> {
+    parse_role_mention(value)
+        .or_else(|| value.parse().ok())
+        .ok_or_else(|| anyhow!("`{value}` is not a role mention or a role id."))
+}
+
+fn parse_roles(value: &str) -> Result<Vec<RoleId>> {
+    value
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|r| !r.is_empty())
+        .map(parse_role)
+        .collect()
+}
+
+pub fn parse_channel(value: &str) -> Result<ChannelId> {
+    parse_channel_mention(value)
+        .or_else(|| value.parse().ok())
+        .ok_or_else(|| anyhow!("`{value}` is not a channel mention or a channel id."))
+}
+
+/// Parse a list of channel mentions or ids separated by spaces or commas.
+pub fn parse_channels(channels: &str) -> Result<Vec<ChannelId>> {
+    channels
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|c| !c.is_empty())
+        .map(parse_channel)
+        .collect()
+}
+
+fn parse_seconds(value: &str) -> Result<TimeDelta> {
+    match value.parse::<u32>() {
+        Ok(seconds) => Ok(TimeDelta::seconds(seconds.into())),
+        Err(_) => bail!("`{value}` is not a valid amount of seconds."),
+    }
+}
+
+/// Parse `points:seconds` pairs like `3:600 5:3600`, sorted by points.
+fn parse_thresholds(value: &str) -> Result<Vec<(i64, TimeDelta)>> {
+    let mut thresholds = value
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|t| !t.is_empty())
+        .map(|threshold| {
+            let parsed = threshold
+                .split_once(':')
+                .and_then(|(points, seconds)| Some((points.parse().ok()?, seconds)));
+            match parsed {
+                Some((points, seconds)) if points > 0 => {
+                    let duration = parse_seconds(seconds)?;
+                    if duration > MAX_TIMEOUT {
+                        bail!(
+                            "`{threshold}` times out for too long, the most is {} seconds.",
+                            MAX_TIMEOUT.num_seconds()
+                        );
+                    }
+                    Ok((points, duration))
+                }
+                _ => bail!("`{threshold}` is not a `points:seconds` pair."),
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    thresholds.sort_by_key(|&(points, _)| points);
+
+    Ok(thresholds)
+}
+
+fn parse_percentage(value: &str) -> Result<u8> {
+    match value.trim_end_matches('%').parse::<u8>() {
+        Ok(percent) if percent <= 100 => Ok(percent),
+        _ => bail!("`{value}` is not a number between 0 and 100."),
+    }
+}
+
+fn parse_optional<T>(value: &str, parse: impl Fn(&str) -> Result<T>) -> Result<Option<T>> {
+    if value.eq_ignore_ascii_case("none") {
+        return Ok(None);
+    }
+
+    parse(value).map(Some)
+}
+
+fn display_seconds(duration: TimeDelta) -> String {
+    format!("{} seconds", duration.num_seconds())
+}
+
+fn display_optional(value: Option<impl Mentionable>) -> String {
+    match value {
+        Some(v) => v.mention().to_string(),
+        None => "Disabled".to_string(),
+    }
+}
+
+pub async fn fetch_guild_configs(db: &SqlitePool) -> Result<HashMap<GuildId, GuildConfig>> {
+    let rows = sqlx::query!("SELECT guild_id, key, value FROM GuildConfig")
+        .fetch_all(db)
+        .await?;
+
+    let mut configs: HashMap<GuildId, GuildConfig> = HashMap::new();
+    for row in rows {
+        let guild_id = GuildId::new(row.guild_id as u64);
+        let Some(key) = ConfigKey::from_name(&row.key) else {
+            eprintln!(
+                "[WARNING] Ignoring unknown config key {} for {guild_id}",
+                row.key
+            );
+            continue;
+        };
+
+        let config = configs
+            .entry(guild_id)
+            .or_insert_with(|| GuildConfig::new(guild_id));
+        if let Err(e) = config.set(key, &row.value) {
+            eprintln!("[WARNING] Ignoring invalid config value for {guild_id}: {e}");
+        }
+    }
+
+    Ok(configs)
+}
+
+/// Validate and persist a new value for `key`, the in-memory config is only
+/// updated once the value was saved.
+pub async fn save_guild_config_value(
+    data: &Data,
+    guild_id: GuildId,
+    key: ConfigKey,
+    value: &str,
+) -> Result<()> {
+    let mut configs = data.guild_configs.write().await;
+    let mut config = configs
+        .get(&guild_id)
+        .cloned()
+        .unwrap_or_else(|| GuildConfig::new(guild_id));
+    config.set(key, value)?;
+
+    let id = guild_id.get() as i64;
+    let name = key.name();
+    let value = value.trim();
+    sqlx::query!(
+        r#"INSERT INTO GuildConfig (guild_id, key, value) VALUES (?, ?, ?)
+        ON CONFLICT(guild_id, key) DO UPDATE SET value = excluded.value"#,
+        id,
+        name,
+        value
+    )
+    .execute(&data.database)
+    .await?;
+
+    configs.insert(guild_id, config);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_accept_mentions_and_ids() {
+        let mut config = GuildConfig::new(GUILD_ID);
+
+        config.set(ConfigKey::SubRole, "<@&1234>").unwrap();
+        assert_eq!(config.sub_role, RoleId::new(1234));
+
+        config.set(ConfigKey::SubRole, "5678").unwrap();
+        assert_eq!(config.sub_role, RoleId::new(5678));
+
+        assert!(config.set(ConfigKey::SubRole, "subs").is_err());
+    }
+
+    #[test]
+    fn streaming_role_can_be_disabled() {
+        let mut config = GuildConfig::new(GUILD_ID);
+        assert_eq!(config.streaming_role, Some(STREAMING_ROLE));
+
+        config.set(ConfigKey::StreamingRole, "none").unwrap();
+        assert_eq!(config.streaming_role, None);
+    }
+
+    #[test]
+    fn streaming_role_is_opt_in_for_other_guilds() {
+        let config = GuildConfig::new(GuildId::new(1));
+        assert_eq!(config.streaming_role, None);
+    }
+
+    #[test]
+    fn gamble_chance_is_a_percentage() {
+        let mut config = GuildConfig::new(GUILD_ID);
+
+        config.set(ConfigKey::GambleFailChance, "42%").unwrap();
+        assert_eq!(config.gamble_fail_chance, 42);
+
+        assert!(config.set(ConfigKey::GambleFailChance, "101").is_err());
+        assert_eq!(config.gamble_fail_chance, 42);
+    }
+
+    #[test]
+    fn every_key_roundtrips_through_its_name() {
+        for key in ConfigKey::all() {
+            assert_eq!(ConfigKey::from_name(key.name()), Some(key));
+        }
+        assert_eq!(ConfigKey::all().count(), 24);
+    }
+
+    #[test]
+    fn starter_roles_are_a_list() {
+        let mut config = GuildConfig::new(GUILD_ID);
+
+        config.set(ConfigKey::StarterRoles, "<@&1>, 2").unwrap();
+        assert_eq!(config.starter_roles, vec![RoleId::new(1), RoleId::new(2)]);
+
+        config.set(ConfigKey::StarterRoles, "none").unwrap();
+        assert!(config.starter_roles.is_empty());
+    }
+
+    #[test]
+    fn warning_thresholds_are_sorted_pairs() {
+        let mut config = GuildConfig::new(GUILD_ID);
+
+        config
+            .set(ConfigKey::WarningThresholds, "5:3600, 3:600")
+            .unwrap();
+        assert_eq!(
+            config.warning_thresholds,
+            vec![(3, TimeDelta::minutes(10)), (5, TimeDelta::hours(1))]
+        );
+
+        assert!(config.set(ConfigKey::WarningThresholds, "3").is_err());
+        assert!(config.set(ConfigKey::WarningThresholds, "0:60").is_err());
+        assert!(config
+            .set(ConfigKey::WarningThresholds, "5:3000000")
+            .is_err());
+    }
+}
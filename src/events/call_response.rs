@@ -1,88 +1,306 @@
-use std::{io::BufReader, path::Path, sync::OnceLock};
+use std::{collections::BTreeMap, path::Path};
 
 use chrono::{DateTime, TimeDelta, Utc};
-use poise::serenity_prelude::{all::Message, Context};
-use regex::Regex;
-use serde::Deserialize;
+use poise::serenity_prelude::{all::Message, ChannelId, Context, GuildId, UserId};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use regex::{Regex, RegexSet};
 use serenity::all::{CreateAttachment, CreateMessage};
-use tokio::{fs::File, io::AsyncReadExt, sync::Mutex};
+use sqlx::SqlitePool;
+use tokio::{
+    fs::File,
+    io::AsyncReadExt,
+    sync::{Mutex, RwLock},
+};
 
-use crate::Result;
+use crate::{config::parse_channels, Result};
 
-#[derive(Debug, Clone, Deserialize)]
-struct Response {
+/// What a response going off puts on cooldown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, poise::ChoiceParameter)]
+pub enum CooldownScope {
+    /// Everywhere in the server
+    Global,
+    /// Only the channel it went off in
+    Channel,
+    /// Only the person who set it off
+    User,
+}
+
+#[derive(Debug, Clone)]
+pub struct CallResponse {
+    pub id: i64,
+    pub expression: String,
+    pub cooldown: i64,
+    pub text: Option<String>,
+    /// Path of a file uploaded for the response
+    pub attachment: Option<String>,
+    /// How likely the response is to be picked when several of them match
+    pub weight: i64,
+    pub cooldown_scope: CooldownScope,
+    /// The response works in every channel when this is empty
+    pub allowed_channels: Vec<ChannelId>,
+    pub denied_channels: Vec<ChannelId>,
+    /// Percent chance of replying when the response is picked
+    pub probability: i64,
+}
+
+struct CallResponseRow {
+    id: i64,
+    guild_id: i64,
     expression: String,
     cooldown: i64,
     text: Option<String>,
     attachment: Option<String>,
+    weight: i64,
+    cooldown_scope: CooldownScope,
+    allowed_channels: Option<String>,
+    denied_channels: Option<String>,
+    probability: i64,
+}
+
+impl From<CallResponseRow> for CallResponse {
+    fn from(row: CallResponseRow) -> Self {
+        Self {
+            id: row.id,
+            expression: row.expression,
+            cooldown: row.cooldown,
+            text: row.text,
+            attachment: row.attachment,
+            weight: row.weight,
+            cooldown_scope: row.cooldown_scope,
+            allowed_channels: channels_from_db(row.allowed_channels.as_deref()),
+            denied_channels: channels_from_db(row.denied_channels.as_deref()),
+            probability: row.probability,
+        }
+    }
 }
 
-const ASSETS_PATH: &str = "./assets/call_responses";
-static RESPONSES: OnceLock<Vec<Response>> = OnceLock::new();
-static LAST_USES: Mutex<Vec<DateTime<Utc>>> = Mutex::const_new(Vec::new());
+impl CallResponse {
+    pub fn is_allowed_in(&self, channel_id: ChannelId) -> bool {
+        (self.allowed_channels.is_empty() || self.allowed_channels.contains(&channel_id))
+            && !self.denied_channels.contains(&channel_id)
+    }
 
-pub async fn import_call_responses() -> Result<()> {
-    let file = std::fs::File::open(Path::new(ASSETS_PATH).join("data.json"))?;
-    let reader = BufReader::new(file);
-    let responses: Vec<Response> = serde_json::from_reader(reader)?;
+    /// Roll whether the response replies this time.
+    pub fn passes_chance(&self, rng: &mut impl Rng) -> bool {
+        rng.gen_range(0..100) < self.probability
+    }
 
-    let mut last_uses = LAST_USES.lock().await;
-    *last_uses = vec![DateTime::<Utc>::MIN_UTC; responses.len()];
+    fn cooldown_key(&self, channel_id: ChannelId, user_id: UserId) -> (i64, u64) {
+        let scope = match self.cooldown_scope {
+            CooldownScope::Global => 0,
+            CooldownScope::Channel => channel_id.get(),
+            CooldownScope::User => user_id.get(),
+        };
 
-    RESPONSES.set(responses).unwrap();
-    Ok(())
+        (self.id, scope)
+    }
 }
 
-pub async fn respond(ctx: &Context, message: &Message) {
-    if message.author.bot {
-        return;
+pub fn channels_to_db(channels: &[ChannelId]) -> Option<String> {
+    if channels.is_empty() {
+        return None;
     }
 
-    for (i, response) in RESPONSES
-        .get()
-        .expect("responses to be initialized")
+    let channels = channels
         .iter()
-        .enumerate()
-    {
-        if is_on_cooldown(i, response.cooldown).await {
-            continue;
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+    Some(channels)
+}
+
+fn channels_from_db(channels: Option<&str>) -> Vec<ChannelId> {
+    let Some(channels) = channels else {
+        return Vec::new();
+    };
+
+    parse_channels(channels).unwrap_or_else(|e| {
+        eprintln!("Found invalid channels for a call response: {e}");
+        Vec::new()
+    })
+}
+
+/// The responses of a guild with their expressions compiled into a single set, so a
+/// message only has to be scanned once.
+pub struct CompiledResponses {
+    /// `None` when the expressions are too big to go in one set
+    set: Option<RegexSet>,
+    expressions: Vec<Regex>,
+    responses: Vec<CallResponse>,
+}
+
+impl CompiledResponses {
+    /// Compile the expressions of the responses, leaving out the ones that aren't valid.
+    pub fn new(responses: Vec<CallResponse>) -> Self {
+        let mut expressions = Vec::with_capacity(responses.len());
+        let mut valid = Vec::with_capacity(responses.len());
+        for response in responses {
+            match Regex::new(&response.expression) {
+                Ok(expression) => {
+                    expressions.push(expression);
+                    valid.push(response);
+                }
+                Err(e) => eprintln!(
+                    "Skipping call response #{} because `{}` is not a valid regex: {e}",
+                    response.id, response.expression
+                ),
+            }
         }
 
-        let expression = &response.expression;
-        let regex =
-            Regex::new(expression).unwrap_or_else(|_| panic!("`{expression}` regex to be valid"));
+        let set = match RegexSet::new(expressions.iter().map(Regex::as_str)) {
+            Ok(set) => Some(set),
+            Err(e) => {
+                eprintln!("Failed to combine the call responses, checking them one by one: {e}");
+                None
+            }
+        };
 
-        if !regex.is_match(&message.content) {
-            continue;
+        Self {
+            set,
+            expressions,
+            responses: valid,
         }
+    }
+
+    /// Every response whose expression matches the message, in the order they were added.
+    pub fn matching<'a>(&'a self, content: &str) -> impl Iterator<Item = &'a CallResponse> {
+        let matches: Vec<usize> = match &self.set {
+            Some(set) => set.matches(content).into_iter().collect(),
+            None => (0..self.expressions.len())
+                .filter(|&i| self.expressions[i].is_match(content))
+                .collect(),
+        };
+
+        matches.into_iter().map(|i| &self.responses[i])
+    }
+}
+
+/// Pick one of the matching responses, where a response with twice the weight is picked
+/// twice as often.
+pub fn pick_weighted<'a>(
+    candidates: &[&'a CallResponse],
+    rng: &mut impl Rng,
+) -> Option<&'a CallResponse> {
+    candidates
+        .choose_weighted(rng, |r| r.weight.max(0) as u64)
+        .ok()
+        .copied()
+}
+
+static RESPONSES: RwLock<BTreeMap<GuildId, CompiledResponses>> = RwLock::const_new(BTreeMap::new());
+/// When each response can go off again, keyed by the response and its cooldown scope
+static COOLDOWNS: Mutex<BTreeMap<(i64, u64), DateTime<Utc>>> = Mutex::const_new(BTreeMap::new());
+
+pub async fn import_call_responses(database: &SqlitePool) -> Result<()> {
+    let rows = sqlx::query_as!(
+        CallResponseRow,
+        r#"SELECT id AS "id!", guild_id, expression, cooldown, text, attachment, weight,
+            cooldown_scope AS "cooldown_scope: CooldownScope", allowed_channels,
+            denied_channels, probability
+        FROM CallResponse
+        ORDER BY id"#
+    )
+    .fetch_all(database)
+    .await?;
+
+    let mut responses = BTreeMap::<GuildId, Vec<CallResponse>>::new();
+    for row in rows {
+        responses
+            .entry(GuildId::new(row.guild_id as u64))
+            .or_default()
+            .push(row.into());
+    }
+
+    *RESPONSES.write().await = responses
+        .into_iter()
+        .map(|(guild_id, responses)| (guild_id, CompiledResponses::new(responses)))
+        .collect();
+    Ok(())
+}
+
+/// Replace the responses of a guild with what's in the database, after they were changed.
+pub async fn reload_call_responses(database: &SqlitePool, guild_id: GuildId) -> Result<()> {
+    let responses = fetch_call_responses(database, guild_id).await?;
+    RESPONSES
+        .write()
+        .await
+        .insert(guild_id, CompiledResponses::new(responses));
+    Ok(())
+}
 
-        match create_message_reply(response).await {
-            None => continue,
-            Some(msg) => {
-                let mut last_uses = LAST_USES.lock().await;
-                last_uses[i] = Utc::now();
+pub async fn fetch_call_responses(
+    database: &SqlitePool,
+    guild_id: GuildId,
+) -> Result<Vec<CallResponse>> {
+    let guild_id = guild_id.get() as i64;
+    let rows = sqlx::query_as!(
+        CallResponseRow,
+        r#"SELECT id AS "id!", guild_id, expression, cooldown, text, attachment, weight,
+            cooldown_scope AS "cooldown_scope: CooldownScope", allowed_channels,
+            denied_channels, probability
+        FROM CallResponse
+        WHERE guild_id = ?
+        ORDER BY id"#,
+        guild_id
+    )
+    .fetch_all(database)
+    .await?;
 
-                if let Err(e) = message.channel_id.send_message(ctx, msg).await {
-                    eprintln!("Failed to send call response message {e:?}");
-                };
+    Ok(rows.into_iter().map(CallResponse::from).collect())
+}
+
+pub async fn respond(ctx: &Context, message: &Message) {
+    if message.author.bot {
+        return;
+    }
+    let Some(guild_id) = message.guild_id else {
+        return;
+    };
+
+    // Don't hold on to the responses while the reply is being sent
+    let response = {
+        let responses = RESPONSES.read().await;
+        let Some(responses) = responses.get(&guild_id) else {
+            return;
+        };
+
+        let mut candidates = Vec::new();
+        for response in responses.matching(&message.content) {
+            let cooldown_key = response.cooldown_key(message.channel_id, message.author.id);
+            if response.is_allowed_in(message.channel_id) && !is_on_cooldown(cooldown_key).await {
+                candidates.push(response);
             }
+        }
+        let mut rng = rand::thread_rng();
+        let picked = pick_weighted(&candidates, &mut rng).filter(|r| r.passes_chance(&mut rng));
+        let Some(response) = picked else {
+            return;
         };
+        response.clone()
+    };
+
+    if let Some(msg) = create_message_reply(&response).await {
+        let cooldown_key = response.cooldown_key(message.channel_id, message.author.id);
+        start_cooldown(cooldown_key, response.cooldown).await;
 
-        break;
+        if let Err(e) = message.channel_id.send_message(ctx, msg).await {
+            eprintln!("Failed to send call response message {e:?}");
+        };
     }
 }
 
-async fn create_message_reply(response: &Response) -> Option<CreateMessage> {
+async fn create_message_reply(response: &CallResponse) -> Option<CreateMessage> {
     let msg = match (&response.text, &response.attachment) {
         (None, None) => {
             eprintln!("Found a call response without anything to reply with {response:?}.");
             return None;
         }
-        (Some(text), Some(filename)) => match get_attachment(filename).await {
+        (Some(text), Some(path)) => match get_attachment(path).await {
             None => return None,
             Some(attachment) => CreateMessage::new().content(text).add_file(attachment),
         },
-        (None, Some(filename)) => match get_attachment(filename).await {
+        (None, Some(path)) => match get_attachment(path).await {
             None => return None,
             Some(attachment) => CreateMessage::new().add_file(attachment),
         },
@@ -92,9 +310,8 @@ async fn create_message_reply(response: &Response) -> Option<CreateMessage> {
     Some(msg)
 }
 
-async fn get_attachment(name: &str) -> Option<CreateAttachment> {
-    let path = Path::new(ASSETS_PATH).join(name);
-    let Ok(mut file) = File::open(&path).await else {
+pub async fn get_attachment(path: &str) -> Option<CreateAttachment> {
+    let Ok(mut file) = File::open(path).await else {
         eprintln!("Failed to open {path:?}");
         return None;
     };
@@ -105,14 +322,138 @@ async fn get_attachment(name: &str) -> Option<CreateAttachment> {
         return None;
     };
 
+    let name = Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string());
     Some(CreateAttachment::bytes(buf, name))
 }
 
-async fn is_on_cooldown(i: usize, cooldown_seconds: i64) -> bool {
-    let last_uses = LAST_USES.lock().await;
+async fn is_on_cooldown(cooldown_key: (i64, u64)) -> bool {
+    COOLDOWNS
+        .lock()
+        .await
+        .get(&cooldown_key)
+        .is_some_and(|&until| until > Utc::now())
+}
+
+async fn start_cooldown(cooldown_key: (i64, u64), cooldown: i64) {
+    let now = Utc::now();
+    let mut cooldowns = COOLDOWNS.lock().await;
+    // Every channel and person gets their own cooldown, so forget the ones that ran out
+    cooldowns.retain(|_, until| *until > now);
+
+    if cooldown > 0 {
+        cooldowns.insert(cooldown_key, now + TimeDelta::seconds(cooldown));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn response(id: i64, expression: &str, weight: i64) -> CallResponse {
+        CallResponse {
+            id,
+            expression: expression.to_string(),
+            cooldown: 0,
+            text: Some("hi".to_string()),
+            attachment: None,
+            weight,
+            cooldown_scope: CooldownScope::Global,
+            allowed_channels: Vec::new(),
+            denied_channels: Vec::new(),
+            probability: 100,
+        }
+    }
+
+    #[test]
+    fn invalid_expressions_are_left_out() {
+        let responses = CompiledResponses::new(vec![
+            response(1, "(?i)tuturu", 1),
+            response(2, "(unclosed", 1),
+            response(3, "tu", 1),
+        ]);
+
+        let matched = responses
+            .matching("TUTURU tu")
+            .map(|r| r.id)
+            .collect::<Vec<_>>();
+        assert_eq!(matched, vec![1, 3]);
+        assert_eq!(responses.matching("nothing").count(), 0);
+    }
+
+    #[test]
+    fn responses_match_without_a_set() {
+        let mut responses =
+            CompiledResponses::new(vec![response(1, "(?i)tuturu", 1), response(2, "tu", 1)]);
+        responses.set = None;
+
+        let matched = responses
+            .matching("TUTURU")
+            .map(|r| r.id)
+            .collect::<Vec<_>>();
+        assert_eq!(matched, vec![1]);
+    }
+
+    #[test]
+    fn weights_decide_how_often_a_response_is_picked() {
+        let heavy = response(1, "a", 3);
+        let light = response(2, "a", 1);
+        let never = response(3, "a", 0);
+        let candidates = [&heavy, &light, &never];
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut picks = [0; 3];
+        for _ in 0..4000 {
+            let picked = pick_weighted(&candidates, &mut rng).unwrap();
+            picks[picked.id as usize - 1] += 1;
+        }
+
+        assert_eq!(picks[2], 0);
+        assert!(picks[0] > picks[1] * 2);
+    }
 
-    last_uses[i]
-        .checked_add_signed(TimeDelta::seconds(cooldown_seconds))
-        .unwrap()
-        > Utc::now()
+    #[test]
+    fn nothing_is_picked_without_candidates() {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(pick_weighted(&[], &mut rng).is_none());
+    }
+
+    #[test]
+    fn channel_lists_limit_where_a_response_works() {
+        let mut response = response(1, "a", 1);
+        assert!(response.is_allowed_in(ChannelId::new(5)));
+
+        response.denied_channels = vec![ChannelId::new(5)];
+        assert!(!response.is_allowed_in(ChannelId::new(5)));
+        assert!(response.is_allowed_in(ChannelId::new(6)));
+
+        response.allowed_channels = vec![ChannelId::new(7)];
+        assert!(!response.is_allowed_in(ChannelId::new(6)));
+        assert!(response.is_allowed_in(ChannelId::new(7)));
+    }
+
+    #[test]
+    fn cooldowns_are_kept_per_scope() {
+        let mut response = response(1, "a", 1);
+        let (channel, user) = (ChannelId::new(5), UserId::new(6));
+
+        assert_eq!(response.cooldown_key(channel, user), (1, 0));
+        response.cooldown_scope = CooldownScope::Channel;
+        assert_eq!(response.cooldown_key(channel, user), (1, 5));
+        response.cooldown_scope = CooldownScope::User;
+        assert_eq!(response.cooldown_key(channel, user), (1, 6));
+    }
+
+    #[test]
+    fn probability_is_a_percentage() {
+        let mut response = response(1, "a", 1);
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!((0..100).all(|_| response.passes_chance(&mut rng)));
+
+        response.probability = 0;
+        assert!((0..100).all(|_| !response.passes_chance(&mut rng)));
+    }
 }
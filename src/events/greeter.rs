@@ -0,0 +1,203 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use poise::serenity_prelude::{all::Message, Context, GuildId, Mentionable, User, UserId};
+use rand::seq::IteratorRandom;
+use sqlx::SqlitePool;
+use tokio::sync::{Mutex, RwLock};
+
+use crate::Result;
+
+/// Someone who gets greeted when they talk.
+#[derive(Debug, Clone)]
+pub struct Greeting {
+    pub user_id: UserId,
+    /// `|` separated greetings, one of them is picked at random
+    pub templates: String,
+    /// Seconds before the same person is greeted again
+    pub timeout: i64,
+    /// Greet the first message of every day instead of waiting for the timeout
+    pub daily: bool,
+}
+
+impl Greeting {
+    pub fn templates(&self) -> impl Iterator<Item = &str> {
+        self.templates
+            .split('|')
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+    }
+
+    fn is_due(&self, last_greeting: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+        let Some(last_greeting) = last_greeting else {
+            return true;
+        };
+
+        if self.daily {
+            last_greeting.date_naive() != now.date_naive()
+        } else {
+            last_greeting + TimeDelta::seconds(self.timeout) <= now
+        }
+    }
+}
+
+static GREETINGS: RwLock<BTreeMap<GuildId, BTreeMap<UserId, Greeting>>> =
+    RwLock::const_new(BTreeMap::new());
+static LAST_GREETINGS: Mutex<BTreeMap<(GuildId, UserId), DateTime<Utc>>> =
+    Mutex::const_new(BTreeMap::new());
+
+pub async fn import_greetings(database: &SqlitePool) -> Result<()> {
+    let rows = sqlx::query!(
+        r#"SELECT guild_id, user_id, templates, timeout, daily AS "daily: bool" FROM Greeting"#
+    )
+    .fetch_all(database)
+    .await?;
+
+    let mut greetings = BTreeMap::<GuildId, BTreeMap<UserId, Greeting>>::new();
+    for row in rows {
+        let user_id = UserId::new(row.user_id as u64);
+        greetings
+            .entry(GuildId::new(row.guild_id as u64))
+            .or_default()
+            .insert(
+                user_id,
+                Greeting {
+                    user_id,
+                    templates: row.templates,
+                    timeout: row.timeout,
+                    daily: row.daily,
+                },
+            );
+    }
+
+    *GREETINGS.write().await = greetings;
+    Ok(())
+}
+
+/// Replace the greetings of a guild with what's in the database, after they were changed.
+pub async fn reload_greetings(database: &SqlitePool, guild_id: GuildId) -> Result<()> {
+    let greetings = fetch_greetings(database, guild_id)
+        .await?
+        .into_iter()
+        .map(|g| (g.user_id, g))
+        .collect();
+
+    GREETINGS.write().await.insert(guild_id, greetings);
+    Ok(())
+}
+
+pub async fn fetch_greetings(database: &SqlitePool, guild_id: GuildId) -> Result<Vec<Greeting>> {
+    let guild_id = guild_id.get() as i64;
+    let rows = sqlx::query!(
+        r#"SELECT user_id, templates, timeout, daily AS "daily: bool" FROM Greeting
+        WHERE guild_id = ?
+        ORDER BY user_id"#,
+        guild_id
+    )
+    .fetch_all(database)
+    .await?;
+
+    let greetings = rows
+        .into_iter()
+        .map(|row| Greeting {
+            user_id: UserId::new(row.user_id as u64),
+            templates: row.templates,
+            timeout: row.timeout,
+            daily: row.daily,
+        })
+        .collect();
+
+    Ok(greetings)
+}
+
+/// Fill in `{user}` and `{user.mention}` in a greeting.
+pub fn render_greeting(template: &str, user: &User) -> String {
+    let name = user.global_name.as_deref().unwrap_or(&user.name);
+
+    template
+        .replace("{user.mention}", &user.id.mention().to_string())
+        .replace("{user}", name)
+}
+
+pub async fn say_hi(ctx: &Context, message: &Message) {
+    if message.author.bot {
+        return;
+    }
+    let Some(guild_id) = message.guild_id else {
+        return;
+    };
+    let user_id = message.author.id;
+
+    let greeting = {
+        let greetings = GREETINGS.read().await;
+        let Some(greeting) = greetings.get(&guild_id).and_then(|g| g.get(&user_id)) else {
+            return;
+        };
+        greeting.clone()
+    };
+
+    let now = Utc::now();
+    {
+        let mut last_greetings = LAST_GREETINGS.lock().await;
+        let last_greeting = last_greetings.get(&(guild_id, user_id)).copied();
+        if !greeting.is_due(last_greeting, now) {
+            return;
+        }
+        last_greetings.insert((guild_id, user_id), now);
+    }
+
+    let Some(template) = greeting.templates().choose(&mut rand::thread_rng()) else {
+        return;
+    };
+    let text = render_greeting(template, &message.author);
+
+    if let Err(e) = message.reply(ctx, text).await {
+        eprintln!("Failed to greet {user_id}: {e:?}");
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn greeting(templates: &str, daily: bool) -> Greeting {
+        Greeting {
+            user_id: UserId::new(1),
+            templates: templates.to_string(),
+            timeout: 60,
+            daily,
+        }
+    }
+
+    fn at(timestamp: &str) -> DateTime<Utc> {
+        timestamp.parse().unwrap()
+    }
+
+    #[test]
+    fn splits_templates() {
+        let greeting = greeting("Hi Blob! | Hey {user}||", false);
+        assert_eq!(
+            greeting.templates().collect::<Vec<_>>(),
+            vec!["Hi Blob!", "Hey {user}"]
+        );
+    }
+
+    #[test]
+    fn waits_for_the_timeout() {
+        let greeting = greeting("hi", false);
+        let last = at("2026-10-18T10:00:00Z");
+
+        assert!(greeting.is_due(None, last));
+        assert!(!greeting.is_due(Some(last), at("2026-10-18T10:00:59Z")));
+        assert!(greeting.is_due(Some(last), at("2026-10-18T10:01:00Z")));
+    }
+
+    #[test]
+    fn daily_greetings_wait_for_the_next_day() {
+        let greeting = greeting("hi", true);
+        let last = at("2026-10-18T23:00:00Z");
+
+        assert!(!greeting.is_due(Some(last), at("2026-10-18T23:59:59Z")));
+        assert!(greeting.is_due(Some(last), at("2026-10-19T00:00:00Z")));
+    }
+}
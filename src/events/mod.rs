@@ -1,14 +1,23 @@
-mod blob;
 mod call_response;
+mod greeter;
 mod streaming;
+mod welcome;
 
 use std::sync::Arc;
 
 use poise::serenity_prelude::{
     all::{Message, Presence},
-    Context,
+    Context, GuildId, Member, User,
 };
 
+use crate::Data;
+
+pub use call_response::{
+    channels_to_db, fetch_call_responses, get_attachment, pick_weighted, reload_call_responses,
+    CallResponse, CompiledResponses, CooldownScope,
+};
+pub use greeter::{fetch_greetings, reload_greetings, Greeting};
+
 pub fn handle_new_message_event(ctx: &Context, message: &Message) {
     let ctx = Arc::new(ctx.clone());
     let message = Arc::new(message.clone());
@@ -17,7 +26,7 @@ pub fn handle_new_message_event(ctx: &Context, message: &Message) {
     tokio::spawn({
         let ctx = Arc::clone(&ctx);
         let message = Arc::clone(&message);
-        async move { blob::say_hi(&ctx, &message).await }
+        async move { greeter::say_hi(&ctx, &message).await }
     });
     tokio::spawn({
         let ctx = Arc::clone(&ctx);
@@ -26,18 +35,47 @@ pub fn handle_new_message_event(ctx: &Context, message: &Message) {
     });
 }
 
-pub fn handle_presence_update(ctx: &Context, new_data: &Presence) {
+pub async fn handle_presence_update(ctx: &Context, user_data: &Data, new_data: &Presence) {
+    let Some(guild_id) = new_data.guild_id else {
+        return;
+    };
+
     let ctx = ctx.clone();
     let new_data = new_data.clone();
+    let config = user_data.guild_config(guild_id).await;
 
     // TODO: When more tasks need to be added ctx/new_data should become Arcs like in `handle_new_message_event`
     tokio::spawn(async move {
-        if let Err(e) = streaming::update_streaming_role_status(&ctx, &new_data).await {
+        if let Err(e) = streaming::update_streaming_role_status(&ctx, &config, &new_data).await {
             eprintln!("Error updating streaming role: {e:?}")
         }
     });
 }
 
-pub async fn initialize_event_data() {
-    call_response::import_call_responses().await.unwrap();
+pub async fn handle_member_addition(ctx: &Context, user_data: &Data, member: &Member) {
+    let config = user_data.guild_config(member.guild_id).await;
+
+    if let Err(e) = welcome::welcome_member(ctx, &config, member).await {
+        eprintln!("Error welcoming {}: {e:?}", member.user.id)
+    }
+}
+
+pub async fn handle_member_removal(
+    ctx: &Context,
+    user_data: &Data,
+    guild_id: GuildId,
+    user: &User,
+) {
+    let config = user_data.guild_config(guild_id).await;
+
+    if let Err(e) = welcome::farewell_member(ctx, &config, guild_id, user).await {
+        eprintln!("Error saying goodbye to {}: {e:?}", user.id)
+    }
+}
+
+pub async fn initialize_event_data(database: &sqlx::SqlitePool) {
+    call_response::import_call_responses(database)
+        .await
+        .unwrap();
+    greeter::import_greetings(database).await.unwrap();
 }
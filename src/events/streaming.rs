@@ -1,37 +1,120 @@
+use std::collections::BTreeSet;
+
 use anyhow::Context as _;
 use poise::serenity_prelude::{
-    all::{ActivityType, Presence},
+    all::{Activity, ActivityType, Presence},
     Context,
 };
+use serenity::all::{CreateMessage, GuildId, Mentionable, UserId};
+use tokio::sync::Mutex;
 
-use crate::{
-    config::{GUILD_ID, STREAMING_ROLE},
-    Result,
-};
+use crate::{config::GuildConfig, Result};
 
-pub async fn update_streaming_role_status(ctx: &Context, new_data: &Presence) -> Result<()> {
+// Members that were live the last time their presence was updated, used to
+// only announce a stream once instead of on every presence update.
+static LIVE_MEMBERS: Mutex<BTreeSet<(GuildId, UserId)>> = Mutex::const_new(BTreeSet::new());
+
+pub async fn update_streaming_role_status(
+    ctx: &Context,
+    config: &GuildConfig,
+    new_data: &Presence,
+) -> Result<()> {
     let Some(guild_id) = new_data.guild_id else {
         return Ok(());
     };
 
-    if guild_id != GUILD_ID {
-        return Ok(());
-    }
+    let user_id = new_data.user.id;
+    let stream = match find_stream(config, new_data) {
+        Some(stream) if has_required_role(ctx, config, guild_id, user_id).await? => Some(stream),
+        _ => None,
+    };
 
-    let has_streaming_activity = new_data
-        .activities
-        .iter()
-        .any(|a| a.kind == ActivityType::Streaming);
+    let Some(stream) = stream else {
+        LIVE_MEMBERS.lock().await.remove(&(guild_id, user_id));
 
-    if has_streaming_activity {
-        ctx.http
-            .add_member_role(guild_id, new_data.user.id, STREAMING_ROLE, None)
+        let Some(streaming_role) = config.streaming_role else {
+            return Ok(());
+        };
+        return ctx
+            .http
+            .remove_member_role(guild_id, user_id, streaming_role, None)
             .await
-            .context("Failed to add streaming role")
-    } else {
+            .context("Failed to remove streaming role");
+    };
+
+    // Servers can announce streams without handing out a role for them
+    if let Some(streaming_role) = config.streaming_role {
         ctx.http
-            .remove_member_role(guild_id, new_data.user.id, STREAMING_ROLE, None)
+            .add_member_role(guild_id, user_id, streaming_role, None)
             .await
-            .context("Failed to remove streaming role")
+            .context("Failed to add streaming role")?;
+    }
+
+    let just_went_live = LIVE_MEMBERS.lock().await.insert((guild_id, user_id));
+    if just_went_live {
+        announce_stream(ctx, config, user_id, stream).await?;
+    }
+
+    Ok(())
+}
+
+fn find_stream<'a>(config: &GuildConfig, new_data: &'a Presence) -> Option<&'a Activity> {
+    new_data.activities.iter().find(|a| {
+        if a.kind != ActivityType::Streaming {
+            return false;
+        }
+
+        let Some(game) = &config.streaming_game else {
+            return true;
+        };
+
+        // For Twitch streams the state is the category the stream is in
+        a.state
+            .as_deref()
+            .is_some_and(|state| state.to_lowercase().contains(&game.to_lowercase()))
+    })
+}
+
+async fn has_required_role(
+    ctx: &Context,
+    config: &GuildConfig,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> Result<bool> {
+    let Some(required_role) = config.streaming_required_role else {
+        return Ok(true);
+    };
+
+    let member = guild_id
+        .member(ctx, user_id)
+        .await
+        .context("Failed to get the streamer's roles")?;
+
+    Ok(member.roles.contains(&required_role))
+}
+
+async fn announce_stream(
+    ctx: &Context,
+    config: &GuildConfig,
+    user_id: UserId,
+    stream: &Activity,
+) -> Result<()> {
+    let Some(channel_id) = config.streaming_announcement_channel else {
+        return Ok(());
+    };
+
+    let mut content = format!("{} is now live!", user_id.mention());
+    if let Some(title) = &stream.details {
+        content.push_str(&format!("\n> {title}"));
     }
+    if let Some(url) = &stream.url {
+        content.push_str(&format!("\n{url}"));
+    }
+
+    channel_id
+        .send_message(ctx, CreateMessage::new().content(content))
+        .await
+        .context("Failed to announce stream")?;
+
+    Ok(())
 }
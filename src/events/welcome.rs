@@ -0,0 +1,76 @@
+use anyhow::Context as _;
+use poise::serenity_prelude::{Context, GuildId, Member, User};
+use serenity::all::{CreateEmbed, CreateMessage};
+
+use super::greeter::render_greeting;
+use crate::{common::avatar_url, config::GuildConfig, Result};
+
+const WELCOME_COLOUR: u32 = 0x57F287;
+const FAREWELL_COLOUR: u32 = 0xED4245;
+
+pub async fn welcome_member(ctx: &Context, config: &GuildConfig, member: &Member) -> Result<()> {
+    // One missing role shouldn't keep the others or the welcome message from going out
+    for role in &config.starter_roles {
+        if let Err(e) = ctx
+            .http
+            .add_member_role(member.guild_id, member.user.id, *role, Some("Starter role"))
+            .await
+        {
+            eprintln!(
+                "Failed to add starter role {role} to {}: {e}",
+                member.user.id
+            );
+        }
+    }
+
+    let Some(channel) = config.welcome_channel else {
+        return Ok(());
+    };
+
+    let description = render_message(ctx, &config.welcome_message, member.guild_id, &member.user);
+    let embed = CreateEmbed::default()
+        .colour(WELCOME_COLOUR)
+        .title("Welcome!")
+        .thumbnail(avatar_url(&member.user))
+        .description(description);
+
+    channel
+        .send_message(ctx, CreateMessage::new().embed(embed))
+        .await
+        .context("Failed to send welcome message")?;
+
+    Ok(())
+}
+
+pub async fn farewell_member(
+    ctx: &Context,
+    config: &GuildConfig,
+    guild_id: GuildId,
+    user: &User,
+) -> Result<()> {
+    let (Some(channel), Some(message)) = (config.welcome_channel, &config.farewell_message) else {
+        return Ok(());
+    };
+
+    let embed = CreateEmbed::default()
+        .colour(FAREWELL_COLOUR)
+        .title("Goodbye!")
+        .thumbnail(avatar_url(user))
+        .description(render_message(ctx, message, guild_id, user));
+
+    channel
+        .send_message(ctx, CreateMessage::new().embed(embed))
+        .await
+        .context("Failed to send farewell message")?;
+
+    Ok(())
+}
+
+/// Fill in the variables of a greeting and `{server}`.
+fn render_message(ctx: &Context, template: &str, guild_id: GuildId, user: &User) -> String {
+    let server = guild_id
+        .name(ctx)
+        .unwrap_or_else(|| "the server".to_string());
+
+    render_greeting(template, user).replace("{server}", &server)
+}
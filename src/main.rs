@@ -3,18 +3,31 @@ mod common;
 pub mod config;
 mod events;
 
-use std::num::NonZeroUsize;
+use std::{collections::HashMap, num::NonZeroUsize};
 
 use anyhow::Result;
 use common::bail_reply;
+use config::GuildConfig;
 use lru::LruCache;
-use poise::serenity_prelude::{self as serenity, FullEvent, GatewayIntents};
+use poise::serenity_prelude::{self as serenity, FullEvent, GatewayIntents, GuildId};
 use tokio::sync::{Mutex, RwLock};
 
 pub struct Data {
     database: sqlx::SqlitePool,
     rpg_summary_cache: Mutex<LruCache<u64, String>>,
     simple_commands: RwLock<commands::SimpleCommands>,
+    guild_configs: RwLock<HashMap<GuildId, GuildConfig>>,
+}
+
+impl Data {
+    pub async fn guild_config(&self, guild_id: GuildId) -> GuildConfig {
+        self.guild_configs
+            .read()
+            .await
+            .get(&guild_id)
+            .cloned()
+            .unwrap_or_else(|| GuildConfig::new(guild_id))
+    }
 }
 pub type Context<'a> = poise::Context<'a, Data, anyhow::Error>;
 pub type Error = anyhow::Error;
@@ -24,7 +37,8 @@ async fn main() {
     let token = std::env::var("DISCORD_TOKEN").expect("missing DISCORD_TOKEN");
     let intents = GatewayIntents::non_privileged()
         | GatewayIntents::MESSAGE_CONTENT
-        | GatewayIntents::GUILD_PRESENCES;
+        | GatewayIntents::GUILD_PRESENCES
+        | GatewayIntents::GUILD_MEMBERS;
 
     let database = sqlx::sqlite::SqlitePoolOptions::new()
         .max_connections(5)
@@ -36,11 +50,15 @@ async fn main() {
         .await
         .expect("Expected to be able to connect to the database");
 
+    let guild_configs = config::fetch_guild_configs(&database)
+        .await
+        .expect("Could not fetch guild configs");
+
     // Initialize default commands
     let commands = commands::initialize_commands(&database).await;
     commands::set_system_commands(&commands);
     // Initialize event data
-    events::initialize_event_data().await;
+    events::initialize_event_data(&database).await;
 
     let options = poise::FrameworkOptions {
         commands,
@@ -60,6 +78,7 @@ async fn main() {
         database,
         rpg_summary_cache: Mutex::new(LruCache::new(NonZeroUsize::new(10).unwrap())),
         simple_commands: RwLock::default(),
+        guild_configs: RwLock::new(guild_configs),
     };
     let framework = poise::Framework::builder()
         .options(options)
@@ -117,7 +136,15 @@ async fn event_event_handler<'a>(
             commands::setup_collectors(ctx, user_data).await;
         }
         FullEvent::Message { new_message } => events::handle_new_message_event(ctx, new_message),
-        FullEvent::PresenceUpdate { new_data } => events::handle_presence_update(ctx, new_data),
+        FullEvent::PresenceUpdate { new_data } => {
+            events::handle_presence_update(ctx, user_data, new_data).await
+        }
+        FullEvent::GuildMemberAddition { new_member } => {
+            events::handle_member_addition(ctx, user_data, new_member).await
+        }
+        FullEvent::GuildMemberRemoval { guild_id, user, .. } => {
+            events::handle_member_removal(ctx, user_data, *guild_id, user).await
+        }
         FullEvent::InteractionCreate { interaction } => {
             commands::try_intercepting_command_call(ctx, user_data, interaction).await?;
         }